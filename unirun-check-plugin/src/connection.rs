@@ -0,0 +1,218 @@
+use std::{
+    fmt,
+    io::{self, ErrorKind, Read, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    path::Path,
+    process::{Child, Command, ExitStatus, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use unirun_if::{
+    constants::SOCKET_BUFFER_SIZE,
+    package::{Package, PackageId, Payload},
+};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Why scenario failed, with the package that broke it
+#[derive(Debug)]
+pub struct Failure {
+    pub reason: String,
+    pub package: Option<String>,
+}
+
+impl Failure {
+    pub fn new(reason: impl Into<String>) -> Self {
+        Self {
+            reason: reason.into(),
+            package: None,
+        }
+    }
+
+    pub fn with_package(reason: impl Into<String>, package: &Package) -> Self {
+        Self {
+            reason: reason.into(),
+            package: Some(serde_json::to_string(package).unwrap_or_default()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Direction {
+    Sent,
+    Received,
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Sent => write!(f, ">"),
+            Self::Received => write!(f, "<"),
+        }
+    }
+}
+
+/// What plugin did while runner waited for a package
+pub enum Received {
+    Package(Box<Package>),
+    Timeout,
+    Closed,
+}
+
+/// Launched plugin and its connection. Plugin is killed on drop
+pub struct Plugin {
+    child: Child,
+    stream: UnixStream,
+    /// Part of frame read before timeout
+    buffer: Vec<u8>,
+    /// Every frame in both directions as JSON
+    pub traffic: Vec<(Direction, String)>,
+    pub verbose: bool,
+}
+
+impl Plugin {
+    /// Starts `command` and waits for it to connect to `listener`
+    pub fn launch(
+        command: &mut Command,
+        listener: &UnixListener,
+        timeout: Duration,
+        verbose: bool,
+    ) -> Result<Self, Failure> {
+        if !verbose {
+            command.stdout(Stdio::null()).stderr(Stdio::null());
+        }
+        let mut child = command
+            .spawn()
+            .map_err(|e| Failure::new(format!("failed to launch: {}", e)))?;
+
+        let deadline = Instant::now() + timeout;
+        let stream = loop {
+            match listener.accept() {
+                Ok((stream, _)) => break stream,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => {}
+                Err(e) => return Err(Failure::new(format!("failed to accept: {}", e))),
+            }
+            if let Ok(Some(status)) = child.try_wait() {
+                return Err(Failure::new(format!(
+                    "exited before connecting, {}",
+                    status
+                )));
+            }
+            if Instant::now() >= deadline {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(Failure::new(format!("didn't connect within {:?}", timeout)));
+            }
+            thread::sleep(POLL_INTERVAL);
+        };
+        stream
+            .set_nonblocking(false)
+            .map_err(|e| Failure::new(e.to_string()))?;
+
+        Ok(Self {
+            child,
+            stream,
+            buffer: Vec::with_capacity(SOCKET_BUFFER_SIZE),
+            traffic: Vec::new(),
+            verbose,
+        })
+    }
+
+    fn log(&mut self, direction: Direction, json: String) {
+        if self.verbose {
+            eprintln!("{} {}", direction, json);
+        }
+        self.traffic.push((direction, json));
+    }
+
+    pub fn send(&mut self, payload: Payload) -> Result<PackageId, Failure> {
+        let package = Package::new(payload);
+        let json = serde_json::to_string(&package).map_err(|e| Failure::new(e.to_string()))?;
+
+        let mut frame = [0; SOCKET_BUFFER_SIZE];
+        let len = json.len().min(SOCKET_BUFFER_SIZE);
+        frame[..len].copy_from_slice(&json.as_bytes()[..len]);
+        self.stream
+            .write_all(&frame)
+            .map_err(|e| Failure::new(format!("failed to send: {}", e)))?;
+
+        self.log(Direction::Sent, json);
+        Ok(package.get_id())
+    }
+
+    /// Answers package that needs only confirmation
+    pub fn confirm(&mut self, package: &Package) -> Result<(), Failure> {
+        self.send(Payload::Result((package.get_id(), Ok(()))))
+            .map(|_| ())
+    }
+
+    /// Next frame until `deadline`. Frame that is not a package fails the scenario
+    pub fn receive(&mut self, deadline: Instant) -> Result<Received, Failure> {
+        while self.buffer.len() < SOCKET_BUFFER_SIZE {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            if timeout.is_zero() {
+                return Ok(Received::Timeout);
+            }
+            self.stream
+                .set_read_timeout(Some(timeout))
+                .map_err(|e| Failure::new(e.to_string()))?;
+
+            let mut chunk = [0; SOCKET_BUFFER_SIZE];
+            let wanted = SOCKET_BUFFER_SIZE - self.buffer.len();
+            match self.stream.read(&mut chunk[..wanted]) {
+                Ok(0) => return Ok(Received::Closed),
+                Ok(read) => self.buffer.extend_from_slice(&chunk[..read]),
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                    return Ok(Received::Timeout)
+                }
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(Failure::new(format!("failed to read: {}", e))),
+            }
+        }
+
+        let frame = std::mem::take(&mut self.buffer);
+        let text = String::from_utf8_lossy(&frame)
+            .trim_end_matches(char::from(0))
+            .trim()
+            .to_owned();
+        self.log(Direction::Received, text.clone());
+
+        match serde_json::from_str::<Package>(&text) {
+            Ok(package) => Ok(Received::Package(Box::new(package))),
+            Err(e) => Err(Failure {
+                reason: format!("malformed package: {}", e),
+                package: Some(text),
+            }),
+        }
+    }
+
+    /// Waits for process to exit. `None` on timeout
+    pub fn wait_exit(&mut self, deadline: Instant) -> io::Result<Option<ExitStatus>> {
+        loop {
+            if let Some(status) = self.child.try_wait()? {
+                return Ok(Some(status));
+            }
+            if Instant::now() >= deadline {
+                return Ok(None);
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+}
+
+impl Drop for Plugin {
+    fn drop(&mut self) {
+        if let Ok(None) = self.child.try_wait() {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+}
+
+/// Listener in place of runner socket
+pub fn listen(socket: &Path) -> io::Result<UnixListener> {
+    let listener = UnixListener::bind(socket)?;
+    listener.set_nonblocking(true)?;
+    Ok(listener)
+}
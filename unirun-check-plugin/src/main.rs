@@ -0,0 +1,140 @@
+mod connection;
+mod scenario;
+
+use std::{env, process::ExitCode, time::Duration};
+
+use connection::{listen, Failure, Plugin};
+use scenario::SCENARIOS;
+use unirun_if::path;
+
+const USAGE: &str = "\
+Usage: unirun-check-plugin [options] <plugin> [args...]
+
+Acts as unirun and runs protocol scenarios against plugin.
+Plugin is launched for every scenario and connects to socket in temporary runtime dir
+
+Options:
+    --timeout <ms>  time given to plugin for every answer, 3000 by default
+    --verbose       print all packages and output of plugin
+    -h, --help      print this help
+
+Scenarios:
+    handshake           connects and optionally registers
+    empty-query         answers GetData with Ok, hits and Abort
+    rapid-requery       answers the last of quickly aborted queries
+    unknown-activation  refuses Activate of hit it never sent with Err
+    configure           answers Configure with Ok or Err
+    quit                exits on Quit";
+
+struct Args {
+    timeout: Duration,
+    verbose: bool,
+    plugin: String,
+    plugin_args: Vec<String>,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Option<Args> {
+    let mut timeout = Duration::from_secs(3);
+    let mut verbose = false;
+    loop {
+        let arg = args.next()?;
+        match arg.as_str() {
+            "--timeout" => timeout = Duration::from_millis(args.next()?.parse().ok()?),
+            "--verbose" => verbose = true,
+            _ if arg.starts_with('-') => return None,
+            _ => {
+                return Some(Args {
+                    timeout,
+                    verbose,
+                    plugin: arg,
+                    plugin_args: args.collect(),
+                })
+            }
+        }
+    }
+}
+
+/// Offending package, or the last ones if failure is about something missing
+fn print_failure(failure: &Failure, plugin: Option<&Plugin>) {
+    println!("      {}", failure.reason);
+    match (&failure.package, plugin) {
+        (Some(package), _) => println!("      {}", package),
+        (None, Some(plugin)) => {
+            let skip = plugin.traffic.len().saturating_sub(5);
+            for (direction, json) in plugin.traffic.iter().skip(skip) {
+                println!("      {} {}", direction, json);
+            }
+        }
+        (None, None) => {}
+    }
+}
+
+fn main() -> ExitCode {
+    let args = match env::args().nth(1).as_deref() {
+        Some("-h" | "--help") => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        _ => match parse_args(env::args().skip(1)) {
+            Some(args) => args,
+            None => {
+                eprintln!("{}", USAGE);
+                return ExitCode::FAILURE;
+            }
+        },
+    };
+
+    // running unirun must not get the plugin, plugin inherits it
+    let runtime = match tempfile::Builder::new()
+        .prefix("unirun-check-plugin-")
+        .tempdir()
+    {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("Failed to create runtime dir: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    env::set_var("XDG_RUNTIME_DIR", runtime.path());
+    let listener = match listen(&path::socket()) {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("Failed to listen plugin socket: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut failed = 0;
+    for (index, scenario) in SCENARIOS.iter().enumerate() {
+        let mut command = std::process::Command::new(&args.plugin);
+        command.args(&args.plugin_args);
+
+        let mut plugin = match Plugin::launch(&mut command, &listener, args.timeout, args.verbose) {
+            Ok(plugin) => plugin,
+            Err(failure) => {
+                println!("FAIL  {}", scenario.name);
+                print_failure(&failure, None);
+                for scenario in &SCENARIOS[index + 1..] {
+                    println!("SKIP  {}", scenario.name);
+                }
+                return ExitCode::FAILURE;
+            }
+        };
+
+        match (scenario.run)(&mut plugin, args.timeout) {
+            Ok(outcome) => println!("PASS  {:<20}{}", scenario.name, outcome),
+            Err(failure) => {
+                failed += 1;
+                println!("FAIL  {}", scenario.name);
+                print_failure(&failure, Some(&plugin));
+            }
+        }
+    }
+
+    if failed == 0 {
+        ExitCode::SUCCESS
+    } else {
+        println!("{} of {} scenarios failed", failed, SCENARIOS.len());
+        ExitCode::FAILURE
+    }
+}
@@ -0,0 +1,318 @@
+use std::{
+    collections::HashSet,
+    time::{Duration, Instant},
+};
+
+use unirun_if::package::{Command, HitId, Package, PackageId, Payload, Query};
+
+use crate::connection::{Failure, Plugin, Received};
+
+/// Short description of what plugin did
+pub type Outcome = Result<String, Failure>;
+
+pub struct Scenario {
+    pub name: &'static str,
+    pub run: fn(&mut Plugin, Duration) -> Outcome,
+}
+
+/// In order of the protocol, the first failing ones explain the following
+pub const SCENARIOS: &[Scenario] = &[
+    Scenario {
+        name: "handshake",
+        run: handshake,
+    },
+    Scenario {
+        name: "empty-query",
+        run: empty_query,
+    },
+    Scenario {
+        name: "rapid-requery",
+        run: rapid_requery,
+    },
+    Scenario {
+        name: "unknown-activation",
+        run: unknown_activation,
+    },
+    Scenario {
+        name: "configure",
+        run: configure,
+    },
+    Scenario {
+        name: "quit",
+        run: quit,
+    },
+];
+
+/// Hits requested per query, like runner does by default
+const LIMIT: usize = 50;
+
+/// How long plugin is given to register after connecting
+const HANDSHAKE_TIME: Duration = Duration::from_millis(300);
+
+/// Confirms packages plugin may send at any time. Returns `false` for the others
+fn handle_any_time(plugin: &mut Plugin, package: &Package) -> Result<bool, Failure> {
+    match package.payload {
+        Payload::Register(_)
+        | Payload::Status(_)
+        | Payload::Item(_)
+        | Payload::ClearItems
+        | Payload::UpdateHit(_)
+        | Payload::RemoveHit(_) => {
+            plugin.confirm(package)?;
+            Ok(true)
+        }
+        _ => Ok(false),
+    }
+}
+
+/// Lets plugin register, like runner does before the first query
+pub fn settle(plugin: &mut Plugin) -> Result<Option<String>, Failure> {
+    let deadline = Instant::now() + HANDSHAKE_TIME;
+    let mut name = None;
+    loop {
+        let package = match plugin.receive(deadline)? {
+            Received::Package(package) => *package,
+            Received::Timeout => return Ok(name),
+            Received::Closed => return Err(Failure::new("closed connection after connecting")),
+        };
+
+        if let Payload::Register(info) = &package.payload {
+            if name.is_some() {
+                return Err(Failure::with_package("registered twice", &package));
+            }
+            name = Some(info.name.clone());
+        }
+        if !handle_any_time(plugin, &package)? {
+            return Err(Failure::with_package(
+                "unexpected package before any command",
+                &package,
+            ));
+        }
+    }
+}
+
+fn handshake(plugin: &mut Plugin, _: Duration) -> Outcome {
+    match settle(plugin)? {
+        Some(name) => Ok(format!("registered as {:?}", name)),
+        None => Ok("connected without Register, named after its process".to_owned()),
+    }
+}
+
+/// Waits for answer of `GetData` with `id` and its hits until plugin finishes with `Abort`.
+/// Hits and answers of `stale` queries before confirmation of the current one are accepted.
+/// Hits don't say which query they belong to, so with `stale` ones late hits can't be told
+/// from the current ones and are only counted.
+/// Returns number of hits, `None` if plugin refused query with `Err`
+fn answer(
+    plugin: &mut Plugin,
+    id: &PackageId,
+    stale: &[PackageId],
+    timeout: Duration,
+) -> Result<Option<usize>, Failure> {
+    let deadline = Instant::now() + timeout;
+    let mut confirmed = false;
+    let mut hits = HashSet::new();
+    let mut count = 0;
+
+    loop {
+        let package = match plugin.receive(deadline)? {
+            Received::Package(package) => *package,
+            Received::Timeout if confirmed => {
+                return Err(Failure::new(format!(
+                    "query wasn't finished with Abort within {:?}",
+                    timeout
+                )))
+            }
+            Received::Timeout => {
+                return Err(Failure::new(format!(
+                    "GetData wasn't answered within {:?}",
+                    timeout
+                )))
+            }
+            Received::Closed => return Err(Failure::new("closed connection during query")),
+        };
+        if handle_any_time(plugin, &package)? {
+            continue;
+        }
+
+        match &package.payload {
+            Payload::Result((answered, result)) if answered == id => {
+                if confirmed {
+                    return Err(Failure::with_package("GetData answered twice", &package));
+                }
+                if result.is_err() {
+                    return Ok(None);
+                }
+                confirmed = true;
+            }
+            Payload::Result((answered, _)) if !confirmed && stale.contains(answered) => {}
+            Payload::Hit(hit) if confirmed => {
+                plugin.confirm(&package)?;
+                count += 1;
+                if !stale.is_empty() {
+                    continue;
+                }
+                if !hits.insert(hit.id.clone()) {
+                    return Err(Failure::with_package(
+                        "the same hit id sent twice for one query",
+                        &package,
+                    ));
+                }
+                if hits.len() > LIMIT {
+                    return Err(Failure::with_package(
+                        format!("more hits than query limit {}", LIMIT),
+                        &package,
+                    ));
+                }
+            }
+            Payload::Hit(_) if !stale.is_empty() => plugin.confirm(&package)?,
+            Payload::Hit(_) => {
+                return Err(Failure::with_package(
+                    "hit sent before GetData was confirmed",
+                    &package,
+                ))
+            }
+            Payload::Command(Command::Abort) if confirmed => return Ok(Some(count)),
+            // finish of aborted query
+            Payload::Command(Command::Abort) if !stale.is_empty() => {}
+            Payload::Command(Command::Abort) => {
+                return Err(Failure::with_package(
+                    "finished before GetData was confirmed",
+                    &package,
+                ))
+            }
+            _ => return Err(Failure::with_package("unexpected package", &package)),
+        }
+    }
+}
+
+fn get_data(plugin: &mut Plugin, text: &str) -> Result<PackageId, Failure> {
+    plugin.send(Payload::Command(Command::GetData(Query {
+        text: text.to_owned(),
+        limit: Some(LIMIT),
+        ..Default::default()
+    })))
+}
+
+fn empty_query(plugin: &mut Plugin, timeout: Duration) -> Outcome {
+    settle(plugin)?;
+
+    let id = get_data(plugin, "")?;
+    match answer(plugin, &id, &[], timeout)? {
+        Some(hits) => Ok(format!("{} hits", hits)),
+        None => Ok("refused with Err".to_owned()),
+    }
+}
+
+/// Every key press of fast typing, runner aborts previous query before the next one
+fn rapid_requery(plugin: &mut Plugin, timeout: Duration) -> Outcome {
+    settle(plugin)?;
+
+    let mut stale = Vec::new();
+    let mut id = get_data(plugin, "a")?;
+    for text in ["ab", "abc", "abcd"] {
+        plugin.send(Payload::Command(Command::Abort))?;
+        stale.push(id);
+        id = get_data(plugin, text)?;
+    }
+
+    match answer(plugin, &id, &stale, timeout)? {
+        Some(hits) => Ok(format!("last query answered with {} hits", hits)),
+        None => Ok("last query refused with Err".to_owned()),
+    }
+}
+
+fn unknown_activation(plugin: &mut Plugin, timeout: Duration) -> Outcome {
+    settle(plugin)?;
+
+    let id = plugin.send(Payload::Command(Command::Activate(HitId::new())))?;
+    let deadline = Instant::now() + timeout;
+    loop {
+        let package = match plugin.receive(deadline)? {
+            Received::Package(package) => *package,
+            Received::Timeout => {
+                return Err(Failure::new(format!(
+                    "Activate wasn't answered within {:?}",
+                    timeout
+                )))
+            }
+            Received::Closed => return Err(Failure::new("closed connection on activation")),
+        };
+        if handle_any_time(plugin, &package)? {
+            continue;
+        }
+
+        return match &package.payload {
+            Payload::Result((answered, Err(message))) if answered == &id => {
+                Ok(format!("refused with {:?}", message))
+            }
+            Payload::Result((answered, Ok(()))) | Payload::Action((answered, _))
+                if answered == &id =>
+            {
+                Err(Failure::with_package(
+                    "activated hit it never sent",
+                    &package,
+                ))
+            }
+            _ => Err(Failure::with_package("unexpected package", &package)),
+        };
+    }
+}
+
+/// Settings from runner config, answer is required even if plugin has none
+fn configure(plugin: &mut Plugin, timeout: Duration) -> Outcome {
+    settle(plugin)?;
+
+    let mut settings = serde_json::Map::new();
+    settings.insert("unirun-check-plugin".to_owned(), true.into());
+    let id = plugin.send(Payload::Command(Command::Configure(settings)))?;
+    let deadline = Instant::now() + timeout;
+    loop {
+        let package = match plugin.receive(deadline)? {
+            Received::Package(package) => *package,
+            Received::Timeout => {
+                return Err(Failure::new(format!(
+                    "Configure wasn't answered within {:?}",
+                    timeout
+                )))
+            }
+            Received::Closed => return Err(Failure::new("closed connection on Configure")),
+        };
+        if handle_any_time(plugin, &package)? {
+            continue;
+        }
+
+        return match &package.payload {
+            Payload::Result((answered, Ok(()))) if answered == &id => Ok("accepted".to_owned()),
+            Payload::Result((answered, Err(message))) if answered == &id => {
+                Ok(format!("refused with {:?}", message))
+            }
+            _ => Err(Failure::with_package("unexpected package", &package)),
+        };
+    }
+}
+
+fn quit(plugin: &mut Plugin, timeout: Duration) -> Outcome {
+    settle(plugin)?;
+
+    let id = plugin.send(Payload::Command(Command::Quit))?;
+    let deadline = Instant::now() + timeout;
+    // answer is optional, plugin may just close connection
+    while let Received::Package(package) = plugin.receive(deadline)? {
+        match &package.payload {
+            Payload::Result((answered, _)) if answered == &id => {}
+            _ if handle_any_time(plugin, &package)? => {}
+            _ => return Err(Failure::with_package("unexpected package", &package)),
+        }
+    }
+
+    match plugin.wait_exit(deadline) {
+        Ok(Some(status)) if status.success() => Ok("exited".to_owned()),
+        Ok(Some(status)) => Err(Failure::new(format!("exited with {}", status))),
+        Ok(None) => Err(Failure::new(format!(
+            "still running {:?} after Quit",
+            timeout
+        ))),
+        Err(e) => Err(Failure::new(format!("failed to wait for exit: {}", e))),
+    }
+}
@@ -0,0 +1,43 @@
+//! Mock plugin follows the protocol, so every scenario must pass.
+//! It's built with `unirun-test-support`, run with `cargo test --workspace`
+
+use std::process::Command;
+
+use unirun_test_support::{mock_plugin_binary, MockScript, SCRIPT_ENV};
+
+fn check(script: &MockScript) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_unirun-check-plugin"))
+        .arg(mock_plugin_binary())
+        .env(SCRIPT_ENV, serde_json::to_string(script).unwrap())
+        .output()
+        .expect("Failed to run unirun-check-plugin");
+    let report = String::from_utf8_lossy(&output.stdout).into_owned();
+    assert!(output.status.success(), "{}", report);
+    report
+}
+
+fn assert_all_pass(report: &str) {
+    let lines = report.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 6, "{}", report);
+    assert!(
+        lines.iter().all(|line| line.starts_with("PASS")),
+        "{}",
+        report
+    );
+}
+
+#[test]
+fn registered_mock_passes() {
+    let script = MockScript::named("mock").with_hits(&["{query} 1", "{query} 2"]);
+    assert_all_pass(&check(&script));
+}
+
+#[test]
+fn slow_unregistered_mock_passes() {
+    let script = MockScript::default()
+        .with_hits(&["{query} 1", "{query} 2", "{query} 3"])
+        .with_hit_delay(std::time::Duration::from_millis(20));
+    let report = check(&script);
+    assert_all_pass(&report);
+    assert!(report.contains("connected without Register"), "{}", report);
+}
@@ -0,0 +1,210 @@
+use std::{collections::HashMap, fs, io};
+
+#[allow(unused_imports)]
+use log::*;
+use serde::Deserialize;
+use unirun_if::path;
+
+/// Runner settings read from `$XDG_CONFIG_HOME/unirun/config.toml`
+///
+/// ```toml
+/// daemon = false
+/// grouped = true
+/// group_limit = 5
+/// limit = 50
+/// notifications = false
+///
+/// [plugins.apps]
+/// priority = 10
+///
+/// [plugins.files]
+/// debounce = 200
+///
+/// [plugins.websearch]
+/// fallback = "always"
+/// # the rest is sent to plugin
+/// engine = "duckduckgo"
+/// ```
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Config {
+    /// Keep running with hidden window instead of quitting. Window is shown with `unirun-ctl show`
+    pub daemon: bool,
+    /// Show results grouped by plugin with section headers
+    pub grouped: bool,
+    /// Hits shown per group until it is expanded
+    pub group_limit: usize,
+    /// Hits requested per page from plugins registered with `paging`. `0` for no limit
+    pub limit: usize,
+    /// Send plugin errors as desktop notifications in addition to the banner inside the window
+    pub notifications: bool,
+    /// Keyed by name plugin registered with
+    pub plugins: HashMap<String, PluginConfig>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            daemon: false,
+            grouped: false,
+            group_limit: 5,
+            limit: 0,
+            notifications: false,
+            plugins: Default::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Defaults are used if file is missing or broken
+    pub fn load() -> Self {
+        let path = path::config();
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Self::default(),
+            Err(e) => {
+                error!("Failed to read config {}: {}", path.display(), e);
+                return Self::default();
+            }
+        };
+
+        let config = toml::from_str::<Self>(&content).unwrap_or_else(|e| {
+            error!("Failed to parse config {}: {}", path.display(), e);
+            Self::default()
+        });
+        for (name, plugin) in &config.plugins {
+            for key in plugin.settings.keys() {
+                if let Some(runner_key) = misspelled_runner_key(key) {
+                    warn!(
+                        "{:?} of [plugins.{}] is sent to plugin as its own setting, did you mean {:?}?",
+                        key, name, runner_key
+                    );
+                }
+            }
+        }
+        config
+    }
+
+    pub fn plugin(&self, name: &str) -> PluginConfig {
+        self.plugins.get(name).cloned().unwrap_or_default()
+    }
+
+    /// Plugin's own settings as sent with `Configure`
+    pub fn settings(&self, name: &str) -> serde_json::Map<String, serde_json::Value> {
+        self.plugins
+            .get(name)
+            .map(|plugin| {
+                plugin
+                    .settings
+                    .iter()
+                    .map(|(key, value)| (key.clone(), to_json(value)))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Page size for plugin. `None` for no limit
+    pub fn limit(&self, name: &str) -> Option<usize> {
+        match self.plugin(name).limit.unwrap_or(self.limit) {
+            0 => None,
+            limit => Some(limit),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct PluginConfig {
+    /// Groups of plugins with higher priority go first
+    pub priority: i32,
+    /// Overrides global `limit`
+    pub limit: Option<usize>,
+    /// Milliseconds to wait for user to stop typing before query is sent. `0` sends it right away
+    pub debounce: u64,
+    /// Only for plugins registered as fallback
+    pub fallback: FallbackMode,
+    /// Keys runner doesn't know, they are for plugin itself
+    #[serde(flatten)]
+    pub settings: toml::Table,
+}
+
+/// Keys of [`PluginConfig`] runner uses itself
+const RUNNER_KEYS: [&str; 4] = ["priority", "limit", "debounce", "fallback"];
+
+/// Runner key that plugin setting `key` differs from by a single typo
+fn misspelled_runner_key(key: &str) -> Option<&'static str> {
+    let lowercase = key.to_lowercase().chars().collect::<Vec<_>>();
+    RUNNER_KEYS.into_iter().find(|runner_key| {
+        key != *runner_key
+            && edit_distance(&lowercase, &runner_key.chars().collect::<Vec<_>>()) <= 1
+    })
+}
+
+/// Insertions, deletions, substitutions and transpositions of adjacent chars turning `a` into `b`
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    // rows `i - 2`, `i - 1` and `i`
+    let mut rows = [
+        vec![0; b.len() + 1],
+        (0..=b.len()).collect(),
+        vec![0; b.len() + 1],
+    ];
+    for i in 1..=a.len() {
+        rows[2][0] = i;
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (rows[1][j] + 1)
+                .min(rows[2][j - 1] + 1)
+                .min(rows[1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(rows[0][j - 2] + 1);
+            }
+            rows[2][j] = distance;
+        }
+        rows.rotate_left(1);
+    }
+    rows[1][b.len()]
+}
+
+/// Dates are passed as their TOML text
+fn to_json(value: &toml::Value) -> serde_json::Value {
+    match value {
+        toml::Value::String(text) => text.clone().into(),
+        toml::Value::Integer(number) => (*number).into(),
+        toml::Value::Float(number) => (*number).into(),
+        toml::Value::Boolean(value) => (*value).into(),
+        toml::Value::Datetime(datetime) => datetime.to_string().into(),
+        toml::Value::Array(values) => values.iter().map(to_json).collect(),
+        toml::Value::Table(table) => serde_json::Value::Object(
+            table
+                .iter()
+                .map(|(key, value)| (key.clone(), to_json(value)))
+                .collect(),
+        ),
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FallbackMode {
+    /// Queried when other plugins finished with no hits
+    #[default]
+    OnEmpty,
+    /// Queried with others, hits are pinned at the bottom
+    Always,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn misspelled_runner_keys() {
+        assert_eq!(misspelled_runner_key("prority"), Some("priority"));
+        assert_eq!(misspelled_runner_key("prioirty"), Some("priority"));
+        assert_eq!(misspelled_runner_key("Limit"), Some("limit"));
+        assert_eq!(misspelled_runner_key("debounced"), Some("debounce"));
+        assert_eq!(misspelled_runner_key("priority"), None);
+        assert_eq!(misspelled_runner_key("engine"), None);
+        assert_eq!(misspelled_runner_key("list"), None);
+    }
+}
@@ -0,0 +1,200 @@
+//! Small fuzzy matcher. Every whitespace separated word of the pattern has to match
+//! as case-insensitive subsequence of the text
+use unirun_if::package::Highlight;
+
+const SCORE_MATCH: i64 = 16;
+const BONUS_CONSECUTIVE: i64 = 8;
+const BONUS_WORD_START: i64 = 12;
+const BONUS_FIRST_CHAR: i64 = 8;
+const PENALTY_GAP: i64 = 1;
+/// Description match is worth less than the same title match
+const DESCRIPTION_DIVIDER: i64 = 2;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Match {
+    pub score: i64,
+    /// Sorted char indices of matched characters
+    pub indices: Vec<usize>,
+}
+
+fn is_word_start(chars: &[char], index: usize) -> bool {
+    match index.checked_sub(1).map(|i| chars[i]) {
+        None => true,
+        Some(prev) => {
+            !prev.is_alphanumeric() || (prev.is_lowercase() && chars[index].is_uppercase())
+        }
+    }
+}
+
+fn lowercase(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+/// Finds `word` in `chars` going forward, then goes backward from the end of found match
+/// to get the shortest window
+fn match_word(word: &[char], chars: &[char]) -> Option<Match> {
+    let mut end = 0;
+    let mut w = 0;
+    for (i, c) in chars.iter().enumerate() {
+        if lowercase(*c) == word[w] {
+            w += 1;
+            if w == word.len() {
+                end = i;
+                break;
+            }
+        }
+    }
+    if w < word.len() {
+        return None;
+    }
+
+    let mut indices = Vec::with_capacity(word.len());
+    let mut w = word.len();
+    for i in (0..=end).rev() {
+        if lowercase(chars[i]) == word[w - 1] {
+            indices.push(i);
+            w -= 1;
+            if w == 0 {
+                break;
+            }
+        }
+    }
+    indices.reverse();
+
+    let mut score = 0;
+    for (n, &i) in indices.iter().enumerate() {
+        score += SCORE_MATCH;
+        if i == 0 {
+            score += BONUS_FIRST_CHAR;
+        }
+        if is_word_start(chars, i) {
+            score += BONUS_WORD_START;
+        }
+        if n > 0 {
+            let gap = (i - indices[n - 1] - 1) as i64;
+            if gap == 0 {
+                score += BONUS_CONSECUTIVE;
+            } else {
+                score -= gap * PENALTY_GAP;
+            }
+        }
+    }
+
+    Some(Match { score, indices })
+}
+
+/// `None` if `text` doesn't match. Empty pattern matches everything with zero score
+pub fn fuzzy_match(pattern: &str, text: &str) -> Option<Match> {
+    let chars = text.chars().collect::<Vec<_>>();
+
+    let mut result = Match {
+        score: 0,
+        indices: Vec::new(),
+    };
+    for word in pattern.split_whitespace() {
+        let word = word.chars().map(lowercase).collect::<Vec<_>>();
+        let m = match_word(&word, &chars)?;
+        result.score += m.score;
+        result.indices.extend(m.indices);
+    }
+    result.indices.sort_unstable();
+    result.indices.dedup();
+
+    Some(result)
+}
+
+/// Joins sorted indices into `[start, end)` ranges
+pub fn to_ranges(indices: &[usize]) -> Vec<(usize, usize)> {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for &i in indices {
+        match ranges.last_mut() {
+            Some((_, end)) if *end == i => *end += 1,
+            _ => ranges.push((i, i + 1)),
+        }
+    }
+    ranges
+}
+
+/// Score of the best matching field with highlight of all matching ones. `None` if nothing matches
+pub fn match_hit(
+    pattern: &str,
+    title: &str,
+    description: Option<&str>,
+) -> Option<(i64, Highlight)> {
+    let title_match = fuzzy_match(pattern, title);
+    let description_match = description.and_then(|description| fuzzy_match(pattern, description));
+
+    let score = match (&title_match, &description_match) {
+        (None, None) => return None,
+        (Some(t), None) => t.score,
+        (None, Some(d)) => d.score / DESCRIPTION_DIVIDER,
+        (Some(t), Some(d)) => t.score.max(d.score / DESCRIPTION_DIVIDER),
+    };
+
+    let ranges = |m: Option<Match>| m.map(|m| to_ranges(&m.indices)).unwrap_or_default();
+    Some((
+        score,
+        Highlight {
+            title: ranges(title_match),
+            description: ranges(description_match),
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score(pattern: &str, text: &str) -> i64 {
+        fuzzy_match(pattern, text).expect("No match").score
+    }
+
+    #[test]
+    fn better_matches_score_higher() {
+        // consecutive beats scattered
+        assert!(score("fire", "Firefox") > score("fire", "Fixture"));
+        // word start beats middle of word
+        assert!(score("fox", "Fox terminal") > score("fox", "Firefox"));
+        assert!(score("term", "GNOME Terminal") > score("term", "Determinator"));
+
+        let (title, _) = match_hit("fire", "Firefox", None).unwrap();
+        let (description, _) = match_hit("fire", "Browser", Some("Firefox")).unwrap();
+        assert!(title > description);
+    }
+
+    #[test]
+    fn every_word_must_match() {
+        assert_eq!(fuzzy_match("xyz", "Firefox"), None);
+        assert_eq!(fuzzy_match("fire chrome", "Firefox"), None);
+        // order of chars inside the word matters
+        assert_eq!(fuzzy_match("oxf", "Firefox"), None);
+        assert_eq!(match_hit("xyz", "Firefox", Some("Web browser")), None);
+        assert_eq!(fuzzy_match("", "Firefox").map(|m| m.score), Some(0));
+    }
+
+    #[test]
+    fn indices_are_chars() {
+        let m = fuzzy_match("éd", "Éditeur de texte").unwrap();
+        assert_eq!(m.indices, [0, 1]);
+
+        let m = fuzzy_match("файл", "Менеджер файлов").unwrap();
+        assert_eq!(m.indices, [9, 10, 11, 12]);
+        assert_eq!(to_ranges(&m.indices), [(9, 13)]);
+    }
+
+    #[test]
+    fn shortest_window_is_highlighted() {
+        let m = match_word(&['a', 'b'], &"a a ab".chars().collect::<Vec<_>>()).unwrap();
+        assert_eq!(m.indices, [4, 5]);
+    }
+
+    #[test]
+    fn adjacent_indices_are_merged() {
+        assert_eq!(to_ranges(&[]), []);
+        assert_eq!(to_ranges(&[0, 1, 2, 5, 7, 8]), [(0, 3), (5, 6), (7, 9)]);
+
+        // words of pattern overlapping in text are joined too
+        let (_, highlight) = match_hit("fi ref", "Firefox", None).unwrap();
+        assert_eq!(highlight.title, [(0, 5)]);
+    }
+}
@@ -0,0 +1,1030 @@
+use std::{
+    cell::{Cell, RefCell},
+    cmp::Reverse,
+    collections::{BTreeMap, HashMap},
+    env::current_exe,
+    fs::read_dir,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    process,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use gio::prelude::*;
+use glib::clone;
+#[allow(unused_imports)]
+use log::*;
+use unirun_if::{
+    control::PluginStats,
+    package::{Action, Command, Hit, HitId, Package, Payload, Preview, Query, Severity, Status},
+    socket::Stream,
+};
+
+use crate::{
+    config::{Config, FallbackMode},
+    fuzzy,
+    markup::plain_text,
+    metrics::Metrics,
+    plugin::Plugin,
+};
+
+/// Hit of the result list with the plugin it came from
+#[derive(Debug, Clone)]
+pub struct PluginHit {
+    pub pid: u64,
+    pub hit: Hit,
+}
+
+/// Changes frontend has to reflect.
+///
+/// Result list changes come as splices, so frontend can keep its own copy of the list in sync
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// `removed` hits starting from `position` are replaced with `added`
+    Splice {
+        position: usize,
+        removed: usize,
+        added: Vec<PluginHit>,
+    },
+    /// Plugin changed hit at `position`
+    Update {
+        position: usize,
+        hit: PluginHit,
+    },
+    Connected(u64),
+    /// Plugin told its name, icon or that it is a fallback
+    Registered(u64),
+    Disconnected(u64),
+    Status {
+        pid: u64,
+        status: Option<Status>,
+    },
+    /// Plugin refused query, activation or settings
+    Error {
+        pid: u64,
+        message: String,
+    },
+    /// Query was sent or some plugin finished it. See [`PluginHost::is_busy`]
+    Progress,
+    /// Runner process asked itself to quit, e.g. on Ctrl-C
+    Quit,
+}
+
+/// Child view pushed by plugin on activation
+struct View {
+    plugin_pid: u64,
+    context: String,
+    title: String,
+    /// Query of the parent view to restore on pop
+    parent_query: String,
+    /// Results of the parent view to restore on pop
+    parent_hits: Vec<PluginHit>,
+}
+
+/// Items usually come in bursts so results are refreshed once for the whole burst
+const ITEMS_REFRESH_DELAY: Duration = Duration::from_millis(50);
+
+/// How long [`PluginHost::preview`] waits for plugin. Selection moves on anyway
+const PREVIEW_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How long [`PluginHost::finalize`] waits for plugins to confirm quit
+const FINALIZE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Connected plugins, current query and the result list aggregated from their answers.
+///
+/// Lives on the thread default main context. Nothing calls back into frontend synchronously,
+/// all changes are delivered through [`PluginHost::events`]
+#[derive(Clone)]
+pub struct PluginHost {
+    inner: Rc<HostInner>,
+}
+
+struct HostInner {
+    config: RefCell<Config>,
+    connections: RefCell<Vec<Plugin>>,
+    results: RefCell<Vec<PluginHit>>,
+    /// Text of the last query sent to plugins
+    query: RefCell<String>,
+    views: RefCell<Vec<View>>,
+    /// Query that was restored on view pop and must not be requested again
+    restored_query: RefCell<Option<String>>,
+    /// Whether fallback plugins already got current query
+    fallbacks_queried: Cell<bool>,
+    /// Metrics of disconnected plugins by name
+    retired_metrics: RefCell<HashMap<String, Metrics>>,
+    events: async_channel::Sender<Event>,
+    receiver: async_channel::Receiver<Event>,
+}
+
+impl PluginHost {
+    pub fn new(config: Config) -> Self {
+        let (events, receiver) = async_channel::unbounded();
+        Self {
+            inner: Rc::new(HostInner {
+                config: RefCell::new(config),
+                connections: Default::default(),
+                results: Default::default(),
+                query: Default::default(),
+                views: Default::default(),
+                restored_query: Default::default(),
+                fallbacks_queried: Default::default(),
+                retired_metrics: Default::default(),
+                events,
+                receiver,
+            }),
+        }
+    }
+
+    /// Every event is received only once, so there should be a single consumer
+    pub fn events(&self) -> async_channel::Receiver<Event> {
+        self.inner.receiver.clone()
+    }
+
+    pub fn config(&self) -> Config {
+        self.inner.config.borrow().clone()
+    }
+
+    /// Plugins get `Configure` if their settings changed
+    pub fn set_config(&self, config: Config) {
+        let old = self.inner.config.replace(config);
+        // settings are sent only after `Register`, as on connect
+        for plugin in self
+            .plugins()
+            .iter()
+            .filter(|plugin| plugin.is_registered())
+        {
+            let name = plugin.name();
+            if old.settings(&name) != self.inner.config.borrow().settings(&name) {
+                self.configure(plugin, true);
+            }
+        }
+    }
+
+    pub fn plugins(&self) -> Vec<Plugin> {
+        self.inner.connections.borrow().clone()
+    }
+
+    pub fn plugin(&self, pid: u64) -> Option<Plugin> {
+        self.inner
+            .connections
+            .borrow()
+            .iter()
+            .find(|plugin| plugin.pid() == pid)
+            .cloned()
+    }
+
+    /// Current result list
+    pub fn results(&self) -> Vec<PluginHit> {
+        self.inner.results.borrow().clone()
+    }
+
+    pub fn current_query(&self) -> String {
+        self.inner.query.borrow().clone()
+    }
+
+    /// Titles of pushed views, root first
+    pub fn view_titles(&self) -> Vec<String> {
+        self.inner
+            .views
+            .borrow()
+            .iter()
+            .map(|view| view.title.clone())
+            .collect()
+    }
+
+    /// Whether some plugin didn't finish current query yet
+    pub fn is_busy(&self) -> bool {
+        self.inner.connections.borrow().iter().any(Plugin::is_busy)
+    }
+
+    /// Metrics of every plugin that connected since start, by name
+    pub fn stats(&self) -> Vec<PluginStats> {
+        let mut metrics = self
+            .inner
+            .retired_metrics
+            .borrow()
+            .iter()
+            .map(|(name, metrics)| (name.clone(), metrics.clone()))
+            .collect::<BTreeMap<_, _>>();
+        for plugin in self.inner.connections.borrow().iter() {
+            metrics
+                .entry(plugin.name())
+                .or_default()
+                .merge(&plugin.metrics());
+        }
+
+        metrics
+            .into_iter()
+            .map(|(name, metrics)| metrics.stats(name))
+            .collect()
+    }
+
+    /// Accepts plugin connections on `socket_path`
+    pub fn listen(&self, socket_path: &Path) -> Result<gio::SocketService, glib::Error> {
+        let socket_service = gio::SocketService::new();
+
+        socket_service.add_address(
+            &gio::UnixSocketAddress::new(socket_path),
+            gio::SocketType::Stream,
+            gio::SocketProtocol::Default,
+            glib::Object::NONE,
+        )?;
+
+        socket_service.connect_incoming(clone!(
+            #[weak(rename_to = inner)]
+            self.inner,
+            #[upgrade_or]
+            false,
+            move |_, connection, _| {
+                PluginHost { inner }.handle_new_connection(Stream::from(connection.clone()));
+                true
+            }
+        ));
+
+        Ok(socket_service)
+    }
+
+    fn handle_new_connection(&self, stream: Stream) {
+        let creds = stream.credentials.unwrap_or_default();
+
+        let pid = creds.pid.expect("Failed to read process ID");
+        if process::id() == pid {
+            let host = self.clone();
+            glib::spawn_future_local(async move {
+                match stream.read_future().await {
+                    Ok(Package {
+                        payload: Payload::Command(Command::Quit),
+                        ..
+                    }) => host.emit(Event::Quit),
+                    Ok(package) => warn!("Unexpected payload from self: {:?}", package.payload),
+                    Err(e) => error!("{}", e),
+                }
+            });
+        } else {
+            let plugin = Plugin::new(stream);
+            self.inner.connections.borrow_mut().push(plugin.clone());
+            self.emit(Event::Connected(plugin.pid()));
+            self.spawn_plugin_reader(plugin.clone());
+            self.query_new_plugin(&plugin);
+        }
+    }
+
+    /// Asks just connected plugin for current query, results of the others stay as they are
+    fn query_new_plugin(&self, plugin: &Plugin) {
+        // pushed view belongs to another plugin
+        if !self.inner.views.borrow().is_empty() {
+            return;
+        }
+
+        let query = self.plugin_query(plugin, Query::from(self.current_query().as_str()));
+        plugin.get_data(query);
+        self.emit(Event::Progress);
+    }
+
+    fn spawn_plugin_reader(&self, plugin: Plugin) {
+        let host = self.clone();
+        glib::spawn_future_local(async move {
+            loop {
+                match plugin.stream().read_future().await {
+                    Ok(package) => host.handle_plugin_package(&plugin, package),
+                    Err(e) => {
+                        warn!("Lost connection to plugin {}: {}", plugin.pid(), e);
+                        break;
+                    }
+                }
+            }
+
+            plugin.close();
+            host.inner
+                .retired_metrics
+                .borrow_mut()
+                .entry(plugin.name())
+                .or_default()
+                .merge(&plugin.metrics());
+            host.inner
+                .connections
+                .borrow_mut()
+                .retain(|p| p.pid() != plugin.pid());
+            host.emit(Event::Disconnected(plugin.pid()));
+            host.on_plugin_finished();
+        });
+    }
+
+    fn handle_plugin_package(&self, plugin: &Plugin, package: Package) {
+        let package_id = package.get_id();
+
+        match package.payload {
+            Payload::Hit(hit) => {
+                plugin.send(Package::new(Payload::Result((package_id, Ok(())))));
+
+                if plugin.is_accepting() && plugin.take_hit() {
+                    plugin.collect(&hit);
+
+                    if plugin.take_stale() {
+                        self.remove_hits(plugin.pid());
+                    }
+                    let hit = with_highlight(hit, &self.inner.query.borrow());
+                    self.add_hits(plugin, vec![hit]);
+                }
+            }
+            Payload::Item(hit) => {
+                plugin.send(Package::new(Payload::Result((package_id, Ok(())))));
+                plugin.set_item(hit);
+                self.schedule_items_refresh(plugin);
+            }
+            Payload::ClearItems => {
+                plugin.send(Package::new(Payload::Result((package_id, Ok(())))));
+                plugin.clear_items();
+                self.schedule_items_refresh(plugin);
+            }
+            Payload::UpdateHit(hit) => {
+                // cached answer would bring old version back
+                plugin.clear_cache();
+                plugin.update_item(&hit);
+
+                let pid = plugin.pid();
+                let hit = with_highlight(hit, &self.inner.query.borrow());
+                let is_updated = |shown: &PluginHit| shown.pid == pid && shown.hit.id == hit.id;
+                let mut found = false;
+
+                for view in self.inner.views.borrow_mut().iter_mut() {
+                    for shown in view.parent_hits.iter_mut().filter(|s| is_updated(s)) {
+                        shown.hit = hit.clone();
+                        found = true;
+                    }
+                }
+                let updated = self
+                    .inner
+                    .results
+                    .borrow_mut()
+                    .iter_mut()
+                    .enumerate()
+                    .filter(|(_, shown)| is_updated(shown))
+                    .map(|(position, shown)| {
+                        shown.hit = hit.clone();
+                        (position, shown.clone())
+                    })
+                    .collect::<Vec<_>>();
+                found |= !updated.is_empty();
+                for (position, hit) in updated {
+                    self.emit(Event::Update { position, hit });
+                }
+
+                let result = if found {
+                    Ok(())
+                } else {
+                    Err(format!("Hit {} is not shown", hit.id))
+                };
+                plugin.send(Package::new(Payload::Result((package_id, result))));
+            }
+            Payload::RemoveHit(id) => {
+                plugin.clear_cache();
+                plugin.remove_item(&id);
+
+                let pid = plugin.pid();
+                let is_removed = |shown: &PluginHit| shown.pid == pid && shown.hit.id == id;
+                let found = self.inner.results.borrow().iter().any(is_removed)
+                    || self
+                        .inner
+                        .views
+                        .borrow()
+                        .iter()
+                        .any(|view| view.parent_hits.iter().any(is_removed));
+
+                self.retain(|shown| !is_removed(shown));
+                for view in self.inner.views.borrow_mut().iter_mut() {
+                    view.parent_hits.retain(|shown| !is_removed(shown));
+                }
+
+                let result = if found {
+                    Ok(())
+                } else {
+                    Err(format!("Hit {} is not shown", id))
+                };
+                plugin.send(Package::new(Payload::Result((package_id, result))));
+            }
+            Payload::Status(status) => {
+                plugin.send(Package::new(Payload::Result((package_id, Ok(())))));
+
+                if let Some(status) = &status {
+                    let level = match status.severity {
+                        Severity::Info => Level::Info,
+                        Severity::Warning => Level::Warn,
+                        Severity::Error => Level::Error,
+                    };
+                    log!(level, "{}: {}", plugin.name(), status.message);
+                }
+                plugin.set_status(status.clone());
+                self.emit(Event::Status {
+                    pid: plugin.pid(),
+                    status,
+                });
+            }
+            Payload::Register(info) => {
+                plugin.send(Package::new(Payload::Result((package_id, Ok(())))));
+                debug!("Plugin {} registered as {:?}", plugin.pid(), info.name);
+                plugin.set_info(info);
+                self.configure(plugin, false);
+
+                // plugin was queried as regular one on connect
+                if self.fallback_mode(plugin) == Some(FallbackMode::OnEmpty) {
+                    plugin.abort();
+                    self.remove_hits(plugin.pid());
+                    self.on_plugin_finished();
+                }
+                self.emit(Event::Registered(plugin.pid()));
+            }
+            // plugin also sends it for aborted queries, so only answer of current one counts
+            Payload::Command(Command::Abort) => {
+                if plugin.is_accepting() {
+                    self.finish_query(plugin, true);
+                }
+            }
+            Payload::Result((id, result)) if plugin.is_current_query(&id) => match result {
+                Ok(()) => plugin.set_accepting(true),
+                Err(message) => {
+                    error!("{} ({}): {}", plugin.name(), plugin.pid(), message);
+                    plugin.count_error();
+                    self.emit(Event::Error {
+                        pid: plugin.pid(),
+                        message,
+                    });
+                    self.finish_query(plugin, false);
+                }
+            },
+            payload => match payload.reply_to().cloned() {
+                Some(id) => {
+                    if let Some(payload) = plugin.resolve(&id, payload) {
+                        debug!(
+                            "Nobody waits for answer from {}: {:?}",
+                            plugin.pid(),
+                            payload
+                        );
+                    }
+                }
+                None => warn!("Unexpected payload from {}: {:?}", plugin.pid(), payload),
+            },
+        }
+    }
+
+    /// Sends plugin its settings from config. Empty ones are sent only if they were `changed`
+    fn configure(&self, plugin: &Plugin, changed: bool) {
+        let settings = self.inner.config.borrow().settings(&plugin.name());
+        if settings.is_empty() && !changed {
+            return;
+        }
+
+        let host = self.clone();
+        let plugin = plugin.clone();
+        glib::spawn_future_local(async move {
+            let response = plugin
+                .request(Payload::Command(Command::Configure(settings)))
+                .await;
+            match response {
+                Some(Payload::Result((_, Ok(())))) | None => {}
+                Some(Payload::Result((_, Err(message)))) => {
+                    error!("{} ({}): {}", plugin.name(), plugin.pid(), message);
+                    plugin.count_error();
+                    host.emit(Event::Error {
+                        pid: plugin.pid(),
+                        message,
+                    });
+                }
+                Some(payload) => warn!("Unexpected answer on configure: {:?}", payload),
+            }
+        });
+    }
+
+    fn schedule_items_refresh(&self, plugin: &Plugin) {
+        if !plugin.schedule_items_refresh() {
+            return;
+        }
+
+        glib::timeout_add_local_once(
+            ITEMS_REFRESH_DELAY,
+            clone!(
+                #[strong(rename_to = host)]
+                self,
+                #[strong]
+                plugin,
+                move || {
+                    plugin.items_refreshed();
+
+                    if !host.inner.views.borrow().is_empty() {
+                        return;
+                    }
+
+                    host.remove_hits(plugin.pid());
+                    plugin.start_page(
+                        &host.plugin_query(&plugin, Query::from(host.current_query().as_str())),
+                    );
+                    host.add_items_page(&plugin);
+                }
+            ),
+        );
+    }
+
+    fn emit(&self, event: Event) {
+        // receiver is kept by host, so channel is never closed
+        let _ = self.inner.events.try_send(event);
+    }
+
+    /// Replaces `removed` hits starting from `position` with `added`
+    fn splice(&self, position: usize, removed: usize, added: Vec<PluginHit>) {
+        if removed == 0 && added.is_empty() {
+            return;
+        }
+
+        self.inner
+            .results
+            .borrow_mut()
+            .splice(position..position + removed, added.iter().cloned());
+        self.emit(Event::Splice {
+            position,
+            removed,
+            added,
+        });
+    }
+
+    /// Removes hits not matching `keep`, one splice per removed run
+    fn retain(&self, keep: impl Fn(&PluginHit) -> bool) {
+        let kept = self
+            .inner
+            .results
+            .borrow()
+            .iter()
+            .map(keep)
+            .collect::<Vec<_>>();
+
+        // from the end, so positions of runs before stay valid
+        let mut end = kept.len();
+        while end > 0 {
+            if kept[end - 1] {
+                end -= 1;
+                continue;
+            }
+            let mut start = end - 1;
+            while start > 0 && !kept[start - 1] {
+                start -= 1;
+            }
+            self.splice(start, end - start, Vec::new());
+            end = start;
+        }
+    }
+
+    /// Hits of fallback plugins stay at the bottom
+    fn add_hits(&self, plugin: &Plugin, hits: Vec<Hit>) {
+        let mut position = self.inner.results.borrow().len();
+        if !plugin.is_fallback() {
+            let results = self.inner.results.borrow();
+            while position > 0
+                && self
+                    .plugin(results[position - 1].pid)
+                    .is_some_and(|plugin| plugin.is_fallback())
+            {
+                position -= 1;
+            }
+        }
+
+        let hits = hits
+            .into_iter()
+            .map(|hit| PluginHit {
+                pid: plugin.pid(),
+                hit,
+            })
+            .collect();
+        self.splice(position, 0, hits);
+    }
+
+    fn remove_hits(&self, pid: u64) {
+        self.retain(|shown| shown.pid != pid);
+    }
+
+    /// `None` for regular plugins
+    fn fallback_mode(&self, plugin: &Plugin) -> Option<FallbackMode> {
+        plugin
+            .is_fallback()
+            .then(|| self.inner.config.borrow().plugin(&plugin.name()).fallback)
+    }
+
+    /// Sends current query to waiting fallback plugins if regular ones are done and found nothing
+    fn query_fallbacks(&self) {
+        if self.inner.fallbacks_queried.get() || !self.inner.views.borrow().is_empty() {
+            return;
+        }
+
+        let connections = self.plugins();
+        let regular_busy = connections
+            .iter()
+            .any(|plugin| !plugin.is_fallback() && plugin.is_busy());
+        let regular_found = self.inner.results.borrow().iter().any(|shown| {
+            self.plugin(shown.pid)
+                .is_some_and(|plugin| !plugin.is_fallback())
+        });
+        if regular_busy || regular_found {
+            return;
+        }
+
+        self.inner.fallbacks_queried.set(true);
+        for plugin in &connections {
+            if self.fallback_mode(plugin) == Some(FallbackMode::OnEmpty) {
+                plugin.get_data(
+                    self.plugin_query(plugin, Query::from(self.current_query().as_str())),
+                );
+            }
+        }
+    }
+
+    fn finish_query(&self, plugin: &Plugin, cache: bool) {
+        plugin.finish(cache);
+        // answer can be empty
+        if plugin.take_stale() {
+            self.remove_hits(plugin.pid());
+        }
+        self.on_plugin_finished();
+    }
+
+    fn on_plugin_finished(&self) {
+        self.query_fallbacks();
+        self.emit(Event::Progress);
+    }
+
+    /// `query` with page size configured for `plugin`. Only plugins registered with `paging`
+    /// get it, static items are paged by runner itself
+    fn plugin_query(&self, plugin: &Plugin, query: Query) -> Query {
+        let paged = plugin.supports_paging() || (query.context.is_none() && plugin.has_items());
+        Query {
+            limit: paged
+                .then(|| self.inner.config.borrow().limit(&plugin.name()))
+                .flatten(),
+            ..query
+        }
+    }
+
+    /// Shows current page of static list plugin items
+    fn add_items_page(&self, plugin: &Plugin) {
+        let Some(page) = plugin.page() else {
+            return;
+        };
+
+        let hits = filter_items(plugin, &page.text)
+            .into_iter()
+            .skip(page.offset)
+            .take_while(|_| plugin.take_hit())
+            .collect::<Vec<_>>();
+        self.add_hits(plugin, hits);
+        plugin.finish(false);
+    }
+
+    /// Stops receiving hits of current query from all plugins
+    fn abort_queries(&self) {
+        for plugin in self.inner.connections.borrow().iter() {
+            plugin.cancel_delayed();
+            plugin.abort();
+            plugin.reset_pages();
+        }
+    }
+
+    /// Sends `text` to plugins in scope of the current view.
+    /// Answers, cached hits and matching static items replace results of previous query
+    pub fn query(&self, text: &str) {
+        if self.inner.restored_query.take().as_deref() == Some(text) {
+            return;
+        }
+
+        self.inner.query.replace(text.to_owned());
+
+        let (scope_pid, context) = match self.inner.views.borrow().last() {
+            Some(view) => (Some(view.plugin_pid), Some(view.context.clone())),
+            None => (None, None),
+        };
+        // hits of plugins in scope stay until plugin answers the new query, so list doesn't blink
+        self.retain(|shown| scope_pid.is_none_or(|pid| shown.pid == pid));
+        let query = Query {
+            text: text.to_owned(),
+            context,
+            ..Default::default()
+        };
+
+        self.inner.fallbacks_queried.set(false);
+        let connections = self.plugins();
+        connections.iter().for_each(Plugin::reset_pages);
+        for plugin in connections
+            .iter()
+            .filter(|plugin| scope_pid.is_none_or(|pid| plugin.pid() == pid))
+        {
+            let query = self.plugin_query(plugin, query.clone());
+            let root = query.context.is_none();
+            // only plugin that is asked again keeps its old hits
+            plugin.take_stale();
+            if root && self.fallback_mode(plugin) == Some(FallbackMode::OnEmpty) {
+                // waits for regular plugins
+                plugin.cancel_delayed();
+                plugin.abort();
+                self.remove_hits(plugin.pid());
+            } else if root && plugin.has_items() {
+                self.remove_hits(plugin.pid());
+                plugin.start_page(&query);
+                self.add_items_page(plugin);
+            } else if let Some(hits) = plugin.restore_cached(&query) {
+                self.remove_hits(plugin.pid());
+                let hits = hits
+                    .into_iter()
+                    .map(|hit| with_highlight(hit, text))
+                    .collect();
+                self.add_hits(plugin, hits);
+            } else {
+                plugin.mark_stale();
+                let debounce = self.inner.config.borrow().plugin(&plugin.name()).debounce;
+                plugin.get_data_delayed(query, Duration::from_millis(debounce));
+            }
+        }
+
+        self.on_plugin_finished();
+    }
+
+    /// Asks current query again. Cached answers of plugin are dropped
+    pub fn refresh(&self, pid: u64) {
+        if let Some(plugin) = self.plugin(pid) {
+            plugin.clear_cache();
+        }
+        self.query(&self.current_query());
+    }
+
+    /// Requests next page from every plugin that may have more hits for current query
+    pub fn load_more(&self) {
+        for plugin in self.plugins() {
+            let Some(page) = plugin.next_page() else {
+                continue;
+            };
+            debug!(
+                "Loading more from {} with offset {}",
+                plugin.pid(),
+                page.offset
+            );
+
+            if page.context.is_none() && plugin.has_items() {
+                plugin.start_page(&page);
+                self.add_items_page(&plugin);
+            } else {
+                plugin.get_data(page);
+            }
+        }
+
+        self.emit(Event::Progress);
+    }
+
+    /// Asks plugin to activate hit. `None` if plugin failed, error is sent as [`Event::Error`]
+    pub async fn activate(&self, pid: u64, id: HitId) -> Option<Action> {
+        let Some(plugin) = self.plugin(pid) else {
+            warn!("Plugin {} is not connected", pid);
+            return None;
+        };
+
+        // TODO need to send Abort before Activate ?
+        let started = Instant::now();
+        let response = plugin
+            .request(Payload::Command(Command::Activate(id)))
+            .await;
+        if response.is_some() {
+            plugin.record_activation(started.elapsed());
+        }
+
+        match response {
+            Some(Payload::Result((_, Ok(())))) => Some(Action::Close),
+            Some(Payload::Action((_, action))) => Some(action),
+            Some(Payload::Result((_, Err(message)))) => {
+                error!("{} ({}): {}", plugin.name(), pid, message);
+                plugin.count_error();
+                self.emit(Event::Error { pid, message });
+                None
+            }
+            Some(payload) => {
+                warn!("Unexpected answer on activation: {:?}", payload);
+                None
+            }
+            None => {
+                warn!("Plugin {} disconnected before answering", pid);
+                None
+            }
+        }
+    }
+
+    /// Requests preview of hit from its plugin. Plugins not registered with `preview` aren't asked
+    pub async fn preview(&self, pid: u64, id: HitId) -> Option<Preview> {
+        let plugin = self.plugin(pid)?;
+        if !plugin.supports_preview() {
+            return None;
+        }
+        let response = plugin
+            .request_with_timeout(Payload::Command(Command::GetPreview(id)), PREVIEW_TIMEOUT)
+            .await?;
+
+        match response {
+            Payload::Preview((_, preview)) => preview,
+            Payload::Result((_, Err(e))) => {
+                warn!("Plugin {} failed to get preview: {}", pid, e);
+                None
+            }
+            payload => {
+                warn!("Unexpected answer on preview: {:?}", payload);
+                None
+            }
+        }
+    }
+
+    /// Opens child view of plugin. Following queries are sent only to it with `context`
+    pub fn push_view(&self, plugin_pid: u64, context: String, title: String) {
+        self.abort_queries();
+
+        debug!("Pushing view {:?} with context {:?}", title, context);
+        let view = View {
+            plugin_pid,
+            context,
+            title,
+            parent_query: self.current_query(),
+            parent_hits: self.results(),
+        };
+        self.inner.views.borrow_mut().push(view);
+    }
+
+    /// Returns to the parent view restoring its query and results.
+    /// Returns `false` if there is no view to pop
+    pub fn pop_view(&self) -> bool {
+        let Some(view) = self.inner.views.borrow_mut().pop() else {
+            return false;
+        };
+        debug!("Popping view {:?}", view.title);
+
+        self.abort_queries();
+
+        let removed = self.inner.results.borrow().len();
+        self.splice(0, removed, view.parent_hits);
+
+        let query = self.inner.query.replace(view.parent_query.clone());
+        if query != view.parent_query {
+            // frontend sets it back to its entry
+            self.inner.restored_query.replace(Some(view.parent_query));
+        }
+
+        true
+    }
+
+    /// Asks all plugins to quit. They are removed once they close connection
+    pub fn quit_plugins(&self) {
+        for plugin in self.inner.connections.borrow().iter() {
+            plugin.send(Package::new(Payload::Command(Command::Quit)));
+        }
+    }
+
+    /// Blocking version of [`PluginHost::quit_plugins`] for shutdown, when main loop doesn't run anymore.
+    /// Iterates main context until every plugin confirmed quit or left, half a second at most
+    pub fn finalize(&self) {
+        let context = glib::MainContext::default();
+        let remaining = Rc::new(Cell::new(0));
+        for plugin in self.plugins() {
+            trace!("Sending quit to plugin {}", plugin.pid());
+            remaining.set(remaining.get() + 1);
+            context.spawn_local(clone!(
+                #[strong]
+                remaining,
+                async move {
+                    plugin.request(Payload::Command(Command::Quit)).await;
+                    remaining.set(remaining.get() - 1);
+                }
+            ));
+        }
+
+        let timed_out = Rc::new(Cell::new(false));
+        let timeout = glib::timeout_add_local_once(
+            FINALIZE_TIMEOUT,
+            clone!(
+                #[strong]
+                timed_out,
+                move || timed_out.set(true)
+            ),
+        );
+        while remaining.get() > 0 && !timed_out.get() {
+            context.iteration(true);
+        }
+        if timed_out.get() {
+            warn!("{} plugins didn't confirm quit", remaining.get());
+        } else {
+            timeout.remove();
+        }
+    }
+}
+
+/// Adds highlight computed against `query` if plugin didn't provide it
+fn with_highlight(mut hit: Hit, query: &str) -> Hit {
+    if hit.highlight.is_none() {
+        hit.highlight = fuzzy::match_hit(
+            query,
+            &plain_text(hit.title.clone(), hit.use_pango),
+            hit.description
+                .clone()
+                .map(|description| plain_text(description, hit.use_pango))
+                .as_deref(),
+        )
+        .map(|(_, highlight)| highlight);
+    }
+    hit
+}
+
+/// Items of the static list plugin matching `text`, best first
+fn filter_items(plugin: &Plugin, text: &str) -> Vec<Hit> {
+    let mut matched = plugin
+        .items()
+        .into_iter()
+        .filter_map(|hit| {
+            let (score, highlight) = fuzzy::match_hit(
+                text,
+                &plain_text(hit.title.clone(), hit.use_pango),
+                hit.description
+                    .clone()
+                    .map(|description| plain_text(description, hit.use_pango))
+                    .as_deref(),
+            )?;
+            Some((score, hit, highlight))
+        })
+        .collect::<Vec<_>>();
+    // stable, so plugin order is kept for equal scores
+    matched.sort_by_key(|(score, ..)| Reverse(*score));
+
+    matched
+        .into_iter()
+        // highlight plugin sent with item doesn't know the query
+        .map(|(_, mut hit, highlight)| {
+            hit.highlight = Some(highlight);
+            hit
+        })
+        .collect()
+}
+
+pub fn launch_plugin(binary: &Path) {
+    if let Err(e) = process::Command::new(binary).spawn() {
+        error!("Failed to launch {}: {}", binary.display(), e);
+    }
+}
+
+/// Plugins are named `unirun-plugin-<name>`, tools like `unirun-check-plugin` are not
+fn is_plugin_binary(file_name: &str) -> bool {
+    file_name
+        .strip_prefix("unirun-plugin-")
+        .is_some_and(|name| !name.is_empty())
+}
+
+/// Every `unirun-plugin-*` executable placed next to the current one
+pub fn plugin_binaries() -> Vec<PathBuf> {
+    let Ok(current_exe_path) = current_exe() else {
+        error!("Failed to get current executable path");
+        return Vec::new();
+    };
+    let Some(current_dir) = current_exe_path.parent() else {
+        error!("Failed to get parent directory of current executable");
+        return Vec::new();
+    };
+    let Ok(entries) = read_dir(current_dir) else {
+        error!("Failed to read directory: {}", current_dir.display());
+        return Vec::new();
+    };
+
+    entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(is_plugin_binary)
+        })
+        .filter(|path| {
+            path.metadata()
+                .is_ok_and(|metadata| metadata.permissions().mode() & 0o111 != 0)
+        })
+        .collect()
+}
+
+pub fn launch_plugins() {
+    for binary in plugin_binaries() {
+        launch_plugin(&binary);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plugin_binary_names() {
+        assert!(is_plugin_binary("unirun-plugin-applications"));
+        assert!(is_plugin_binary("unirun-plugin-web-search"));
+        assert!(!is_plugin_binary("unirun-plugin-"));
+        assert!(!is_plugin_binary("unirun-plugin"));
+        assert!(!is_plugin_binary("unirun-pluginx"));
+        assert!(!is_plugin_binary("unirun-check-plugin"));
+        assert!(!is_plugin_binary("unirun-mock-plugin"));
+        assert!(!is_plugin_binary("unirun"));
+    }
+}
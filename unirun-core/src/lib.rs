@@ -0,0 +1,12 @@
+//! Plugin host of unirun without any UI.
+//!
+//! [`PluginHost`] accepts plugin connections, sends queries to them and keeps the aggregated
+//! result list. Frontend mirrors that list from [`Event`]s and routes user actions back to the host
+pub mod config;
+pub mod fuzzy;
+pub mod host;
+pub mod markup;
+pub mod metrics;
+pub mod plugin;
+
+pub use host::{launch_plugin, launch_plugins, plugin_binaries, Event, PluginHit, PluginHost};
@@ -0,0 +1,99 @@
+/// `text` without markup if `use_pango`
+///
+/// Tags are dropped and entities are decoded. Broken markup is returned as is
+pub fn plain_text(text: String, use_pango: bool) -> String {
+    if !use_pango {
+        return text;
+    }
+
+    let mut plain = String::with_capacity(text.len());
+    let mut rest = text.as_str();
+    while let Some(i) = rest.find(['<', '&']) {
+        plain.push_str(&rest[..i]);
+        rest = &rest[i..];
+
+        if rest.starts_with('<') {
+            let Some(end) = rest.find('>') else {
+                return text;
+            };
+            rest = &rest[end + 1..];
+        } else {
+            let Some(end) = rest.find(';') else {
+                return text;
+            };
+            let Some(c) = decode_entity(&rest[1..end]) else {
+                return text;
+            };
+            plain.push(c);
+            rest = &rest[end + 1..];
+        }
+    }
+    plain.push_str(rest);
+    plain
+}
+
+/// Character of `entity` given without `&` and `;`
+pub fn decode_entity(entity: &str) -> Option<char> {
+    match entity {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        _ => {
+            let code = entity.strip_prefix('#')?;
+            let code = match code.strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => code.parse().ok()?,
+            };
+            char::from_u32(code)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plain(markup: &str) -> String {
+        plain_text(markup.to_owned(), true)
+    }
+
+    #[test]
+    fn tags_are_dropped() {
+        assert_eq!(plain("<b>Fire</b>fox"), "Firefox");
+        assert_eq!(
+            plain("<span weight=\"bold\"><i>nested</i> tags</span>"),
+            "nested tags"
+        );
+        // tags are not checked to be closed
+        assert_eq!(plain("<b>unclosed"), "unclosed");
+        // not markup
+        assert_eq!(plain_text("<b>Fire</b>".to_owned(), false), "<b>Fire</b>");
+    }
+
+    #[test]
+    fn entities_are_decoded() {
+        assert_eq!(plain("Tom &amp; Jerry &lt;3"), "Tom & Jerry <3");
+        assert_eq!(plain("&#65;&#x42;&#X43;"), "ABC");
+        assert_eq!(plain("&#x1F525;"), "🔥");
+    }
+
+    #[test]
+    fn broken_markup_is_kept() {
+        assert_eq!(plain("a <b"), "a <b");
+        assert_eq!(plain("Tom & Jerry"), "Tom & Jerry");
+        assert_eq!(plain("&unknown;"), "&unknown;");
+    }
+
+    #[test]
+    fn numeric_entities() {
+        assert_eq!(decode_entity("#38"), Some('&'));
+        assert_eq!(decode_entity("#x26"), Some('&'));
+        assert_eq!(decode_entity("#xD800"), None);
+        assert_eq!(decode_entity("#x110000"), None);
+        assert_eq!(decode_entity("#"), None);
+        assert_eq!(decode_entity("#12a"), None);
+        assert_eq!(decode_entity("nbsp"), None);
+    }
+}
@@ -0,0 +1,118 @@
+//! Latency and throughput of plugins, to find the one that makes typing feel slow
+use std::time::Duration;
+
+use unirun_if::control::{PluginStats, Timing};
+
+/// Durations of one kind of request
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Durations {
+    count: u32,
+    total: Duration,
+    max: Duration,
+}
+
+impl Durations {
+    pub fn add(&mut self, duration: Duration) {
+        self.count += 1;
+        self.total += duration;
+        self.max = self.max.max(duration);
+    }
+
+    fn merge(&mut self, other: &Self) {
+        self.count += other.count;
+        self.total += other.total;
+        self.max = self.max.max(other.max);
+    }
+
+    fn timing(&self) -> Timing {
+        Timing {
+            count: self.count,
+            average_ms: match self.count {
+                0 => 0.0,
+                count => self.total.as_secs_f64() * 1000.0 / count as f64,
+            },
+            max_ms: self.max.as_secs_f64() * 1000.0,
+        }
+    }
+}
+
+/// Counters of plugin connection. Connections of the same plugin are merged by its name
+#[derive(Debug, Default, Clone)]
+pub struct Metrics {
+    pub connections: u32,
+    /// Queries plugin finished or refused
+    pub queries: u32,
+    /// Queries runner aborted before plugin finished them
+    pub aborted: u32,
+    /// Hits of finished queries
+    pub hits: u64,
+    pub first_hit: Durations,
+    pub completion: Durations,
+    pub activation: Durations,
+    pub errors: u32,
+}
+
+impl Metrics {
+    pub fn merge(&mut self, other: &Self) {
+        self.connections += other.connections;
+        self.queries += other.queries;
+        self.aborted += other.aborted;
+        self.hits += other.hits;
+        self.first_hit.merge(&other.first_hit);
+        self.completion.merge(&other.completion);
+        self.activation.merge(&other.activation);
+        self.errors += other.errors;
+    }
+
+    pub fn stats(&self, name: String) -> PluginStats {
+        PluginStats {
+            name,
+            queries: self.queries,
+            aborted: self.aborted,
+            hits_per_query: match self.queries {
+                0 => 0.0,
+                queries => self.hits as f64 / queries as f64,
+            },
+            first_hit: self.first_hit.timing(),
+            completion: self.completion.timing(),
+            activation: self.activation.timing(),
+            errors: self.errors,
+            restarts: self.connections.saturating_sub(1),
+        }
+    }
+}
+
+/// Human readable table, one plugin per row. Times are average/max in ms
+pub fn table(stats: &[PluginStats]) -> String {
+    let mut table = format!(
+        "{:<16}{:>8}{:>8}{:>8}{:>14}{:>14}{:>14}{:>8}{:>10}\n",
+        "plugin",
+        "queries",
+        "aborted",
+        "hits",
+        "first hit",
+        "complete",
+        "activate",
+        "errors",
+        "restarts"
+    );
+    let timing = |timing: &Timing| match timing.count {
+        0 => "-".to_owned(),
+        _ => format!("{:.0}/{:.0}", timing.average_ms, timing.max_ms),
+    };
+    for plugin in stats {
+        table.push_str(&format!(
+            "{:<16}{:>8}{:>8}{:>8.1}{:>14}{:>14}{:>14}{:>8}{:>10}\n",
+            plugin.name,
+            plugin.queries,
+            plugin.aborted,
+            plugin.hits_per_query,
+            timing(&plugin.first_hit),
+            timing(&plugin.completion),
+            timing(&plugin.activation),
+            plugin.errors,
+            plugin.restarts
+        ));
+    }
+    table
+}
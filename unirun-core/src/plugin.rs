@@ -0,0 +1,549 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::{HashMap, VecDeque},
+    fs,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+#[allow(unused_imports)]
+use log::*;
+use unirun_if::{
+    package::{Command, Hit, HitId, Icon, Package, PackageId, Payload, PluginInfo, Query, Status},
+    socket::Stream,
+};
+
+use crate::metrics::Metrics;
+
+const CACHE_CAPACITY: usize = 32;
+
+/// Text and context of the query
+type CacheKey = (String, Option<String>);
+
+struct CachedHits {
+    hits: Vec<Hit>,
+    exhausted: bool,
+}
+
+/// Least recently used answers of the plugin
+#[derive(Default)]
+struct HitCache {
+    entries: HashMap<CacheKey, CachedHits>,
+    order: VecDeque<CacheKey>,
+}
+
+impl HitCache {
+    fn key(query: &Query) -> CacheKey {
+        (query.text.clone(), query.context.clone())
+    }
+
+    fn get(&mut self, query: &Query) -> Option<&CachedHits> {
+        let key = Self::key(query);
+        if let Some(position) = self.order.iter().position(|k| *k == key) {
+            let key = self.order.remove(position).unwrap();
+            self.order.push_back(key);
+        }
+        self.entries.get(&key)
+    }
+
+    fn insert(&mut self, query: &Query, hits: CachedHits) {
+        let key = Self::key(query);
+        if self.entries.insert(key.clone(), hits).is_some() {
+            return;
+        }
+
+        self.order.push_back(key);
+        if self.order.len() > CACHE_CAPACITY {
+            if let Some(evicted) = self.order.pop_front() {
+                self.entries.remove(&evicted);
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+    }
+}
+
+/// Runner side of the plugin connection.
+///
+/// Packages are written one by one from the queue as stream can't have more than one pending write.
+/// All reading is done by a single loop of [`PluginHost`](crate::PluginHost) that routes answers
+/// to [`Plugin::request`] callers and hits to the current query
+#[derive(Clone)]
+pub struct Plugin {
+    inner: Rc<PluginInner>,
+}
+
+struct PluginInner {
+    stream: Stream,
+    pid: u64,
+    outgoing: async_channel::Sender<Package>,
+    pending: RefCell<HashMap<PackageId, async_channel::Sender<Payload>>>,
+    query: RefCell<Option<PackageId>>,
+    accepting: Cell<bool>,
+    /// Last requested page of current query
+    page: RefCell<Option<Query>>,
+    /// Hits of current query shown from all pages
+    received: Cell<usize>,
+    /// Plugin has no more hits for current query
+    exhausted: Cell<bool>,
+    /// Hits of current query to put into cache. `None` if some of them are not cacheable
+    collected: RefCell<Option<Vec<Hit>>>,
+    cache: RefCell<HitCache>,
+    /// Hits shown for this plugin belong to previous query and go away with the first new one
+    stale: Cell<bool>,
+    /// Debounced `GetData`
+    delayed: RefCell<Option<glib::SourceId>>,
+    /// Static list of items filtered by runner. `None` if plugin answers `GetData` by itself
+    items: RefCell<Option<Vec<Hit>>>,
+    items_refresh_scheduled: Cell<bool>,
+    info: RefCell<Option<PluginInfo>>,
+    status: RefCell<Option<Status>>,
+    metrics: RefCell<Metrics>,
+    /// When `GetData` of current query was sent
+    query_started: Cell<Option<Instant>>,
+    /// Hits of current query counted for metrics
+    query_hits: Cell<u64>,
+}
+
+impl Plugin {
+    pub fn new(stream: Stream) -> Self {
+        let pid = stream
+            .credentials
+            .and_then(|credentials| credentials.pid)
+            .expect("Failed to read process ID") as u64;
+
+        let (outgoing, receiver) = async_channel::unbounded::<Package>();
+        glib::spawn_future_local(glib::clone!(
+            #[strong]
+            stream,
+            async move {
+                while let Ok(package) = receiver.recv().await {
+                    if let Err(e) = stream.write_future(package).await {
+                        error!("Failed to write to plugin {}: {}", pid, e);
+                        break;
+                    }
+                }
+            }
+        ));
+
+        Self {
+            inner: Rc::new(PluginInner {
+                stream,
+                pid,
+                outgoing,
+                pending: Default::default(),
+                query: Default::default(),
+                accepting: Default::default(),
+                page: Default::default(),
+                received: Default::default(),
+                exhausted: Default::default(),
+                collected: Default::default(),
+                cache: Default::default(),
+                stale: Default::default(),
+                delayed: Default::default(),
+                items: Default::default(),
+                items_refresh_scheduled: Default::default(),
+                info: Default::default(),
+                status: Default::default(),
+                metrics: RefCell::new(Metrics {
+                    connections: 1,
+                    ..Default::default()
+                }),
+                query_started: Default::default(),
+                query_hits: Default::default(),
+            }),
+        }
+    }
+
+    pub fn pid(&self) -> u64 {
+        self.inner.pid
+    }
+
+    pub fn stream(&self) -> &Stream {
+        &self.inner.stream
+    }
+
+    pub fn set_info(&self, info: PluginInfo) {
+        self.inner.info.replace(Some(info));
+    }
+
+    pub fn is_registered(&self) -> bool {
+        self.inner.info.borrow().is_some()
+    }
+
+    /// Registered name. Process name if plugin didn't register
+    pub fn name(&self) -> String {
+        if let Some(info) = self.inner.info.borrow().as_ref() {
+            return info.name.clone();
+        }
+
+        fs::read_to_string(format!("/proc/{}/comm", self.pid()))
+            .map(|comm| comm.trim().to_owned())
+            .unwrap_or_else(|_| self.pid().to_string())
+    }
+
+    pub fn is_fallback(&self) -> bool {
+        self.inner
+            .info
+            .borrow()
+            .as_ref()
+            .is_some_and(|info| info.fallback)
+    }
+
+    pub fn supports_paging(&self) -> bool {
+        self.inner
+            .info
+            .borrow()
+            .as_ref()
+            .is_some_and(|info| info.paging)
+    }
+
+    pub fn supports_preview(&self) -> bool {
+        self.inner
+            .info
+            .borrow()
+            .as_ref()
+            .is_some_and(|info| info.preview)
+    }
+
+    pub fn icon(&self) -> Option<Icon> {
+        self.inner
+            .info
+            .borrow()
+            .as_ref()
+            .and_then(|info| info.icon.clone())
+    }
+
+    /// Kept to be shown once UI is built
+    pub fn status(&self) -> Option<Status> {
+        self.inner.status.borrow().clone()
+    }
+
+    pub fn set_status(&self, status: Option<Status>) {
+        self.inner.status.replace(status);
+    }
+
+    pub fn metrics(&self) -> Metrics {
+        self.inner.metrics.borrow().clone()
+    }
+
+    /// Plugin refused query, activation or settings
+    pub fn count_error(&self) {
+        self.inner.metrics.borrow_mut().errors += 1;
+    }
+
+    pub fn record_activation(&self, time: Duration) {
+        self.inner.metrics.borrow_mut().activation.add(time);
+    }
+
+    pub fn send(&self, package: Package) {
+        if self.inner.outgoing.try_send(package).is_err() {
+            warn!("Plugin {} is disconnected", self.pid());
+        }
+    }
+
+    /// Sends `payload` and waits for the answer referencing it. `None` if plugin disconnects
+    pub async fn request(&self, payload: Payload) -> Option<Payload> {
+        let (_, receiver) = self.send_request(payload);
+        receiver.recv().await.ok()
+    }
+
+    /// [`Plugin::request`] that gives up after `timeout`. Late answer is dropped then
+    pub async fn request_with_timeout(
+        &self,
+        payload: Payload,
+        timeout: Duration,
+    ) -> Option<Payload> {
+        let (id, receiver) = self.send_request(payload);
+        match glib::future_with_timeout(timeout, receiver.recv()).await {
+            Ok(answer) => answer.ok(),
+            Err(_) => {
+                self.inner.pending.borrow_mut().remove(&id);
+                warn!("Plugin {} didn't answer in {:?}", self.pid(), timeout);
+                None
+            }
+        }
+    }
+
+    fn send_request(&self, payload: Payload) -> (PackageId, async_channel::Receiver<Payload>) {
+        let package = Package::new(payload);
+        let id = package.get_id();
+        let (sender, receiver) = async_channel::bounded(1);
+        self.inner.pending.borrow_mut().insert(id.clone(), sender);
+
+        self.send(package);
+        (id, receiver)
+    }
+
+    /// Passes answer to the waiting [`Plugin::request`]. Returns it back if nobody waits for it
+    pub fn resolve(&self, id: &PackageId, payload: Payload) -> Option<Payload> {
+        match self.inner.pending.borrow_mut().remove(id) {
+            Some(sender) => {
+                let _ = sender.try_send(payload);
+                None
+            }
+            None => Some(payload),
+        }
+    }
+
+    /// Aborts current query and requests new data. Hits of previous queries are ignored from now on
+    pub fn get_data(&self, query: Query) {
+        self.abort();
+        self.start_page(&query);
+
+        let request = Package::new(Payload::Command(Command::GetData(query)));
+        self.inner.query.replace(Some(request.get_id()));
+        self.inner.query_started.set(Some(Instant::now()));
+        self.inner.query_hits.set(0);
+        self.send(request);
+    }
+
+    /// Sends `query` after `delay` unless another one comes first
+    pub fn get_data_delayed(&self, query: Query, delay: Duration) {
+        self.cancel_delayed();
+        if delay.is_zero() {
+            self.get_data(query);
+            return;
+        }
+
+        // hits of previous query are not needed anyway
+        self.abort();
+        let source = glib::timeout_add_local_once(
+            delay,
+            glib::clone!(
+                #[strong(rename_to = plugin)]
+                self,
+                move || {
+                    plugin.inner.delayed.take();
+                    plugin.get_data(query);
+                }
+            ),
+        );
+        self.inner.delayed.replace(Some(source));
+    }
+
+    pub fn cancel_delayed(&self) {
+        if let Some(source) = self.inner.delayed.take() {
+            source.remove();
+        }
+    }
+
+    pub fn abort(&self) {
+        if self.inner.query.take().is_some() {
+            self.inner.metrics.borrow_mut().aborted += 1;
+        }
+        self.inner.query_started.take();
+        self.inner.accepting.set(false);
+        self.send(Package::new(Payload::Command(Command::Abort)));
+    }
+
+    pub fn is_current_query(&self, id: &PackageId) -> bool {
+        self.inner.query.borrow().as_ref() == Some(id)
+    }
+
+    /// Whether hits of current query are expected. Plugin confirmed `GetData` and didn't finish yet
+    pub fn is_accepting(&self) -> bool {
+        self.inner.accepting.get()
+    }
+
+    pub fn set_accepting(&self, value: bool) {
+        self.inner.accepting.set(value);
+    }
+
+    /// Plugin is done with current query. Collected hits are cached if `cache`
+    pub fn finish(&self, cache: bool) {
+        self.inner.query.take();
+        self.inner.accepting.set(false);
+
+        if let Some(started) = self.inner.query_started.take() {
+            let mut metrics = self.inner.metrics.borrow_mut();
+            metrics.queries += 1;
+            metrics.hits += self.inner.query_hits.get();
+            metrics.completion.add(started.elapsed());
+        }
+
+        let page_end = self
+            .inner
+            .page
+            .borrow()
+            .as_ref()
+            .and_then(|page| page.limit.map(|limit| page.offset + limit));
+        self.inner
+            .exhausted
+            .set(page_end.is_none_or(|page_end| self.inner.received.get() < page_end));
+
+        let collected = self.inner.collected.take();
+        if let (true, Some(hits), Some(page)) = (cache, collected, self.page()) {
+            self.inner.cache.borrow_mut().insert(
+                &page,
+                CachedHits {
+                    hits,
+                    exhausted: self.inner.exhausted.get(),
+                },
+            );
+        }
+    }
+
+    /// Remembers hit of current query for cache
+    pub fn collect(&self, hit: &Hit) {
+        let mut collected = self.inner.collected.borrow_mut();
+        if hit.no_cache {
+            collected.take();
+        } else if let Some(collected) = collected.as_mut() {
+            collected.push(hit.clone());
+        }
+    }
+
+    /// Cached hits of `query`. Page state is restored as if they came from plugin
+    pub fn restore_cached(&self, query: &Query) -> Option<Vec<Hit>> {
+        let (hits, exhausted) = {
+            let mut cache = self.inner.cache.borrow_mut();
+            let cached = cache.get(query)?;
+            (cached.hits.clone(), cached.exhausted)
+        };
+
+        self.cancel_delayed();
+        self.abort();
+        self.start_page(query);
+        self.inner.received.set(hits.len());
+        self.inner.exhausted.set(exhausted);
+        Some(hits)
+    }
+
+    pub fn clear_cache(&self) {
+        self.inner.cache.borrow_mut().clear();
+    }
+
+    pub fn mark_stale(&self) {
+        self.inner.stale.set(true);
+    }
+
+    /// Whether hits of previous query still have to be removed
+    pub fn take_stale(&self) -> bool {
+        self.inner.stale.replace(false)
+    }
+
+    /// Starts counting hits of `query` page. First page resets the count
+    pub fn start_page(&self, query: &Query) {
+        if query.offset == 0 {
+            self.inner.received.set(0);
+            self.inner.collected.replace(Some(Vec::new()));
+        }
+        self.inner.exhausted.set(false);
+        self.inner.page.replace(Some(query.clone()));
+    }
+
+    pub fn page(&self) -> Option<Query> {
+        self.inner.page.borrow().clone()
+    }
+
+    /// Counts hit of the current page. `false` if page is full and hit should be dropped
+    pub fn take_hit(&self) -> bool {
+        let full = self.inner.page.borrow().as_ref().is_some_and(|page| {
+            page.limit
+                .is_some_and(|limit| self.inner.received.get() >= page.offset + limit)
+        });
+        if !full {
+            self.inner.received.set(self.inner.received.get() + 1);
+
+            if let Some(started) = self.inner.query_started.get() {
+                if self
+                    .inner
+                    .query_hits
+                    .replace(self.inner.query_hits.get() + 1)
+                    == 0
+                {
+                    self.inner
+                        .metrics
+                        .borrow_mut()
+                        .first_hit
+                        .add(started.elapsed());
+                }
+            }
+        }
+        !full
+    }
+
+    /// Query for the page after the last one. `None` if plugin is busy or has nothing more
+    pub fn next_page(&self) -> Option<Query> {
+        if self.is_busy() || self.inner.exhausted.get() {
+            return None;
+        }
+
+        let page = self.inner.page.borrow().clone()?;
+        page.limit?;
+        Some(Query {
+            offset: self.inner.received.get(),
+            ..page
+        })
+    }
+
+    /// Forgets pages of previous query. Plugin doesn't take part in the current one
+    pub fn reset_pages(&self) {
+        self.inner.page.take();
+        self.inner.received.set(0);
+        self.inner.exhausted.set(false);
+    }
+
+    /// Whether current query is not finished yet or waits to be sent
+    pub fn is_busy(&self) -> bool {
+        self.inner.query.borrow().is_some() || self.inner.delayed.borrow().is_some()
+    }
+
+    pub fn has_items(&self) -> bool {
+        self.inner.items.borrow().is_some()
+    }
+
+    pub fn items(&self) -> Vec<Hit> {
+        self.inner.items.borrow().clone().unwrap_or_default()
+    }
+
+    pub fn set_item(&self, hit: Hit) {
+        let mut items = self.inner.items.borrow_mut();
+        let items = items.get_or_insert_with(Vec::new);
+        match items.iter_mut().find(|item| item.id == hit.id) {
+            Some(item) => *item = hit,
+            None => items.push(hit),
+        }
+    }
+
+    /// Replaces item with the same id if there is one
+    pub fn update_item(&self, hit: &Hit) {
+        if let Some(items) = self.inner.items.borrow_mut().as_mut() {
+            if let Some(item) = items.iter_mut().find(|item| item.id == hit.id) {
+                *item = hit.clone();
+            }
+        }
+    }
+
+    pub fn remove_item(&self, id: &HitId) {
+        if let Some(items) = self.inner.items.borrow_mut().as_mut() {
+            items.retain(|item| item.id != *id);
+        }
+    }
+
+    pub fn clear_items(&self) {
+        self.inner.items.replace(Some(Vec::new()));
+    }
+
+    /// Marks items refresh as scheduled. Returns `false` if it already was
+    pub fn schedule_items_refresh(&self) -> bool {
+        !self.inner.items_refresh_scheduled.replace(true)
+    }
+
+    pub fn items_refreshed(&self) {
+        self.inner.items_refresh_scheduled.set(false);
+    }
+
+    /// Drops all waiting requests. Called when connection is lost
+    pub fn close(&self) {
+        self.cancel_delayed();
+        self.inner.pending.borrow_mut().clear();
+        self.inner.outgoing.close();
+        self.inner.query.take();
+        self.inner.accepting.set(false);
+    }
+}
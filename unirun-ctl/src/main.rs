@@ -0,0 +1,83 @@
+use std::{
+    env,
+    error::Error,
+    io::{BufRead, BufReader, Write},
+    os::unix::net::UnixStream,
+    process::ExitCode,
+};
+
+use unirun_if::{
+    control::{Request, Response},
+    path,
+};
+
+const USAGE: &str = "\
+Usage: unirun-ctl <command>
+
+Commands:
+    show                    show window
+    hide                    hide window
+    toggle                  show or hide window
+    query <text>            show window with text in the entry
+    reload-config           read config file again
+    reload-plugins          restart all plugins
+    list-plugins            connected plugins
+    restart-plugin <name>   restart plugin by its name
+    status                  state of the runner
+    stats                   latency and throughput of plugins
+
+Answer is printed as JSON";
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Option<Request> {
+    let request = match args.next()?.as_str() {
+        "show" => Request::Show,
+        "hide" => Request::Hide,
+        "toggle" => Request::Toggle,
+        "query" => Request::Query(args.collect::<Vec<_>>().join(" ")),
+        "reload-config" => Request::ReloadConfig,
+        "reload-plugins" => Request::ReloadPlugins,
+        "list-plugins" => Request::ListPlugins,
+        "restart-plugin" => Request::RestartPlugin(args.next()?),
+        "status" => Request::Status,
+        "stats" => Request::Stats,
+        _ => return None,
+    };
+    Some(request)
+}
+
+fn send(request: &Request) -> Result<Response, Box<dyn Error>> {
+    let mut stream = UnixStream::connect(path::control_socket())
+        .map_err(|e| format!("unirun is not running: {}", e))?;
+
+    let mut json = serde_json::to_string(request)?;
+    json.push('\n');
+    stream.write_all(json.as_bytes())?;
+
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line)?;
+    Ok(serde_json::from_str(&line)?)
+}
+
+fn main() -> ExitCode {
+    let Some(request) = parse_args(env::args().skip(1)) else {
+        eprintln!("{}", USAGE);
+        return ExitCode::FAILURE;
+    };
+
+    match send(&request) {
+        Ok(response) => {
+            println!(
+                "{}",
+                serde_json::to_string(&response).expect("response is serializable")
+            );
+            match response {
+                Response::Error(_) => ExitCode::FAILURE,
+                _ => ExitCode::SUCCESS,
+            }
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,37 @@
+//! Writes JSON Schema of unirun messages
+use std::{env, fs, path::PathBuf, process::ExitCode};
+
+use unirun_interface::schema;
+
+const USAGE: &str = "\
+Usage: unirun-schema [dir]
+
+Writes JSON Schema of every message type into dir.
+Without dir prints schema of plugin package";
+
+fn main() -> ExitCode {
+    let Some(arg) = env::args().nth(1) else {
+        let (_, package) = &schema::all()[0];
+        println!("{}", schema::to_string(package));
+        return ExitCode::SUCCESS;
+    };
+    if arg == "-h" || arg == "--help" {
+        println!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+
+    let dir = PathBuf::from(arg);
+    if let Err(e) = fs::create_dir_all(&dir) {
+        eprintln!("Failed to create {}: {}", dir.display(), e);
+        return ExitCode::FAILURE;
+    }
+    for (file_name, schema) in schema::all() {
+        let path = dir.join(file_name);
+        if let Err(e) = fs::write(&path, schema::to_string(&schema)) {
+            eprintln!("Failed to write {}: {}", path.display(), e);
+            return ExitCode::FAILURE;
+        }
+        println!("{}", path.display());
+    }
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,114 @@
+//! Requests of `unirun-ctl` to running unirun. Each side writes one JSON object per line
+use serde::{Deserialize, Serialize};
+
+use crate::package::Status;
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum Request {
+    #[serde(rename = "show")]
+    Show,
+
+    #[serde(rename = "hide")]
+    Hide,
+
+    #[serde(rename = "toggle")]
+    Toggle,
+
+    /// Shows window with `text` in the entry
+    #[serde(rename = "query")]
+    Query(String),
+
+    #[serde(rename = "reload_config")]
+    ReloadConfig,
+
+    /// Stops all plugins and launches them again
+    #[serde(rename = "reload_plugins")]
+    ReloadPlugins,
+
+    #[serde(rename = "list_plugins")]
+    ListPlugins,
+
+    /// Restarts plugin by its name
+    #[serde(rename = "restart_plugin")]
+    RestartPlugin(String),
+
+    #[serde(rename = "status")]
+    Status,
+
+    /// Latency and throughput of plugins since runner started
+    #[serde(rename = "stats")]
+    Stats,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum Response {
+    #[serde(rename = "ok")]
+    Ok,
+
+    #[serde(rename = "error")]
+    Error(String),
+
+    #[serde(rename = "plugins")]
+    Plugins(Vec<PluginEntry>),
+
+    #[serde(rename = "status")]
+    Status(RunnerStatus),
+
+    #[serde(rename = "stats")]
+    Stats(Vec<PluginStats>),
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PluginEntry {
+    pub pid: u64,
+    pub name: String,
+    pub fallback: bool,
+    /// Working on current query
+    pub busy: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<Status>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RunnerStatus {
+    pub visible: bool,
+    pub query: String,
+    pub hits: u32,
+    pub plugins: usize,
+    /// Titles of pushed views
+    pub views: Vec<String>,
+}
+
+/// Metrics of plugin, connections of the same name are summed up
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PluginStats {
+    pub name: String,
+    /// Queries plugin finished or refused
+    pub queries: u32,
+    /// Queries runner aborted before plugin finished them, e.g. on typing
+    pub aborted: u32,
+    pub hits_per_query: f64,
+    /// From `GetData` to the first hit
+    pub first_hit: Timing,
+    /// From `GetData` to `Abort` or `Err`
+    pub completion: Timing,
+    /// From `Activate` to the answer
+    pub activation: Timing,
+    /// Refused queries, activations and settings
+    pub errors: u32,
+    /// Times plugin connected again after the first time
+    pub restarts: u32,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Timing {
+    pub count: u32,
+    pub average_ms: f64,
+    pub max_ms: f64,
+}
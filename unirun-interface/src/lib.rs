@@ -1,4 +1,8 @@
+pub mod control;
 pub mod package;
+pub mod record;
+#[cfg(feature = "schema")]
+pub mod schema;
 
 pub mod constants {
     pub const DOMAIN: &str = "com.bzglve";
@@ -26,12 +30,28 @@ pub mod path {
     pub fn socket() -> PathBuf {
         runtime().join(format!("{}.sock", MAIN_APP_ID))
     }
+
+    /// Socket of `unirun-ctl` requests
+    pub fn control_socket() -> PathBuf {
+        runtime().join(format!("{}.ctl.sock", MAIN_APP_ID))
+    }
+
+    pub fn config() -> PathBuf {
+        glib::user_config_dir().join("unirun").join("config.toml")
+    }
 }
 
 pub mod socket {
-    use std::error::Error;
+    use std::{
+        error::Error,
+        io::{self, Read, Write},
+    };
 
-    use crate::{constants::SOCKET_BUFFER_SIZE, package::Package};
+    use crate::{
+        constants::SOCKET_BUFFER_SIZE,
+        package::Package,
+        record::{self, Direction},
+    };
 
     fn bytes_to_string(bytes: &[u8]) -> String {
         String::from_utf8_lossy(bytes)
@@ -47,12 +67,26 @@ pub mod socket {
         buffer
     }
 
+    /// Reads one frame from blocking std stream. Frame is returned as is, without parsing
+    pub fn read_frame(reader: &mut impl Read) -> io::Result<String> {
+        let mut buffer = [0; SOCKET_BUFFER_SIZE];
+        reader.read_exact(&mut buffer)?;
+        Ok(bytes_to_string(&buffer))
+    }
+
+    /// Writes `data` as one frame to blocking std stream
+    pub fn write_frame(writer: &mut impl Write, data: &[u8]) -> io::Result<()> {
+        writer.write_all(&create_buffer(data))
+    }
+
     pub type Stream = GStream;
 
     #[derive(Clone)]
     pub struct GStream {
         inner: gio::SocketConnection,
         pub credentials: Option<Credentials>,
+        /// Shared by clones, see [`crate::record`]
+        connection: u64,
     }
 
     impl GStream {
@@ -68,6 +102,7 @@ pub mod socket {
             Ok(Self {
                 inner,
                 credentials: credentals,
+                connection: record::next_connection(),
             })
         }
 
@@ -82,6 +117,7 @@ pub mod socket {
             Ok(Self {
                 inner,
                 credentials: credentals,
+                connection: record::next_connection(),
             })
         }
 
@@ -92,6 +128,7 @@ pub mod socket {
 
             let buffer = stream.read_bytes(SOCKET_BUFFER_SIZE, gio::Cancellable::NONE)?;
             let json = bytes_to_string(&buffer);
+            self.record(Direction::In, &json);
             let package = serde_json::from_str::<Package>(&json)?;
             Ok(package)
         }
@@ -105,6 +142,7 @@ pub mod socket {
                 .read_bytes_future(SOCKET_BUFFER_SIZE, glib::Priority::DEFAULT)
                 .await?;
             let json = bytes_to_string(&buffer);
+            self.record(Direction::In, &json);
             let package = serde_json::from_str::<Package>(&json)?;
             Ok(package)
         }
@@ -117,6 +155,7 @@ pub mod socket {
             let json = serde_json::to_string(&package)?;
             let buffer = create_buffer(json.as_ref());
             stream.write_bytes(&glib::Bytes::from(&buffer), gio::Cancellable::NONE)?;
+            self.record(Direction::Out, &json);
             Ok(())
         }
 
@@ -130,9 +169,18 @@ pub mod socket {
             stream
                 .write_bytes_future(&glib::Bytes::from(&buffer), glib::Priority::DEFAULT)
                 .await?;
+            self.record(Direction::Out, &json);
             Ok(())
         }
 
+        fn record(&self, direction: Direction, json: &str) {
+            // closed stream reads empty
+            if !json.is_empty() {
+                let pid = self.credentials.and_then(|credentials| credentials.pid);
+                record::log(self.connection, pid, direction, json);
+            }
+        }
+
         fn credentials(socket: gio::Socket) -> Option<Credentials> {
             use gio::prelude::SocketExt;
 
@@ -150,6 +198,7 @@ pub mod socket {
             Self {
                 inner: value.clone(),
                 credentials: Self::credentials(value.socket()),
+                connection: record::next_connection(),
             }
         }
     }
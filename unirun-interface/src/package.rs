@@ -1,12 +1,16 @@
-pub use hit::{Hit, HitId};
-pub use package::{Command, Package, PackageId, Payload};
+pub use hit::{Highlight, Hit, HitId, Icon, Preview};
+pub use package::{
+    Action, ClipboardContent, Command, Package, PackageId, Payload, PluginInfo, Progress, Query,
+    Severity, Status,
+};
 use serde::{Deserialize, Serialize};
 pub use uuid::Uuid;
 
 mod uuid {
     use super::*;
 
-    #[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
+    #[derive(Deserialize, Serialize, PartialEq, Eq, Hash, Debug, Clone)]
+    #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
     pub struct Uuid(String);
 
     impl Uuid {
@@ -36,14 +40,75 @@ mod uuid {
 
 #[allow(clippy::module_inception)]
 mod package {
-    use hit::{Hit, HitId};
+    use hit::{Hit, HitId, Icon};
 
     use super::*;
 
     #[doc(alias = "Uuid")]
     pub type PackageId = Uuid;
 
+    /// Query with text only is sent as plain string, as runner sent `GetData` before,
+    /// so plugins that don't know about views and pages keep working
+    #[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Default)]
+    #[serde(remote = "Self")]
+    #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+    pub struct Query {
+        pub text: String,
+
+        /// Context of the view pushed by plugin with [`Action::Push`]. `None` for the root view
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub context: Option<String>,
+
+        /// Max number of hits runner accepts for this query. `None` for no limit
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub limit: Option<usize>,
+
+        /// Number of hits to skip. Runner asks for the next page with the same query
+        /// and `offset` equal to the number of hits it already has
+        #[serde(default, skip_serializing_if = "is_zero")]
+        pub offset: usize,
+    }
+
+    fn is_zero(value: &usize) -> bool {
+        *value == 0
+    }
+
+    impl Serialize for Query {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            if self.context.is_none() && self.limit.is_none() && self.offset == 0 {
+                return serializer.serialize_str(&self.text);
+            }
+            Query::serialize(self, serializer)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Query {
+        fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            #[derive(Deserialize)]
+            #[serde(untagged)]
+            enum Wire {
+                Plain(String),
+                Full(#[serde(with = "Query")] Query),
+            }
+
+            Ok(match Wire::deserialize(deserializer)? {
+                Wire::Plain(text) => Query::from(text.as_str()),
+                Wire::Full(query) => query,
+            })
+        }
+    }
+
+    impl From<&str> for Query {
+        fn from(value: &str) -> Self {
+            Self {
+                text: value.to_owned(),
+                ..Default::default()
+            }
+        }
+    }
+
     #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
     pub enum Command {
         #[serde(rename = "quit")]
         Quit,
@@ -52,13 +117,168 @@ mod package {
         Activate(HitId),
 
         #[serde(rename = "get_data")]
-        GetData(String),
+        GetData(Query),
 
         #[serde(rename = "abort")]
         Abort,
+
+        /// Answered with [`Payload::Preview`]. Sent for hits without embedded `preview`
+        /// to plugins registered with `preview` only
+        #[serde(rename = "get_preview")]
+        GetPreview(HitId),
+
+        /// Settings from `[plugins.<name>]` table of runner config, without keys runner uses itself.
+        /// Sent after [`Payload::Register`] if there are any and again when they change on reload
+        #[serde(rename = "configure")]
+        Configure(serde_json::Map<String, serde_json::Value>),
+    }
+
+    /// What runner should do after plugin handled [`Command::Activate`]
+    ///
+    /// Plain `Result((id, Ok(())))` answer is treated as [`Action::Close`]
+    #[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Default)]
+    #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+    pub enum Action {
+        #[default]
+        #[serde(rename = "close")]
+        Close,
+
+        #[serde(rename = "keep_open")]
+        KeepOpen,
+
+        /// Replace entry text with new query
+        #[serde(rename = "set_query")]
+        SetQuery(String),
+
+        /// Put content into clipboard. Runner keeps it alive for a while even if it `close`s
+        #[serde(rename = "clipboard")]
+        Clipboard {
+            content: ClipboardContent,
+            #[serde(default)]
+            close: bool,
+        },
+
+        /// Show message inside runner window
+        #[serde(rename = "message")]
+        Message(String),
+
+        /// Request data again with the same query
+        #[serde(rename = "refresh")]
+        Refresh,
+
+        /// Open child view. Following queries are sent only to this plugin with `context`
+        /// until user goes back. `title` is shown in breadcrumbs
+        #[serde(rename = "push")]
+        Push { context: String, title: String },
+    }
+
+    /// Content that plugin asks runner to put into clipboard
+    #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+    pub enum ClipboardContent {
+        #[serde(rename = "text")]
+        Text(String),
+
+        /// File content offered with `mime_type` (guessed if missing) alongside the file itself
+        #[serde(rename = "file")]
+        File {
+            path: String,
+            mime_type: Option<String>,
+        },
+
+        /// Text data offered with custom `mime_type`, e.g. `text/uri-list`
+        #[serde(rename = "data")]
+        Data { mime_type: String, data: String },
+    }
+
+    /// How plugin is presented in runner. Sent once with [`Payload::Register`] after connecting
+    #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+    pub struct PluginInfo {
+        /// Shown in group header and used as key of plugin section in runner config
+        pub name: String,
+
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub icon: Option<Icon>,
+
+        /// Plugin answers anything (web search, shell command). It gets `GetData` only when
+        /// other plugins found nothing, unless runner config pins it to the bottom of results
+        #[serde(default)]
+        pub fallback: bool,
+
+        /// Plugin answers `GetData` by pages of `Query.limit` hits starting at `Query.offset`.
+        /// Others get query without them and send all hits at once
+        #[serde(default)]
+        pub paging: bool,
+
+        /// Plugin answers `GetPreview`. Others can only embed preview into hits
+        #[serde(default)]
+        pub preview: bool,
+    }
+
+    impl PluginInfo {
+        pub fn new(name: &str, icon: Option<&str>) -> Self {
+            Self {
+                name: name.to_owned(),
+                icon: icon.map(Icon::from),
+                fallback: false,
+                paging: false,
+                preview: false,
+            }
+        }
+    }
+
+    #[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+    #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+    pub enum Severity {
+        #[default]
+        #[serde(rename = "info")]
+        Info,
+
+        #[serde(rename = "warning")]
+        Warning,
+
+        #[serde(rename = "error")]
+        Error,
+    }
+
+    #[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+    #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+    pub struct Progress {
+        pub done: u64,
+        pub total: u64,
     }
 
+    /// State of the plugin shown in runner status bar, e.g. "Indexing files"
     #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+    pub struct Status {
+        #[serde(default)]
+        pub severity: Severity,
+        pub message: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub progress: Option<Progress>,
+    }
+
+    impl Status {
+        pub fn new(severity: Severity, message: &str) -> Self {
+            Self {
+                severity,
+                message: message.to_owned(),
+                progress: None,
+            }
+        }
+
+        pub fn with_progress(self, done: u64, total: u64) -> Self {
+            Self {
+                progress: Some(Progress { done, total }),
+                ..self
+            }
+        }
+    }
+
+    #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
     pub enum Payload {
         #[serde(rename = "command")]
         Command(Command),
@@ -68,9 +288,51 @@ mod package {
 
         #[serde(rename = "hit")]
         Hit(Hit),
+
+        #[serde(rename = "action")]
+        Action((PackageId, Action)),
+
+        #[serde(rename = "preview")]
+        Preview((PackageId, Option<Preview>)),
+
+        /// Adds or replaces (by id) item of static list. Can be sent at any time.
+        /// Plugin that has static list doesn't get `GetData` at root view, runner filters items by itself
+        #[serde(rename = "item")]
+        Item(Hit),
+
+        /// Empties static list
+        #[serde(rename = "clear_items")]
+        ClearItems,
+
+        /// Replaces fields of shown hit with the same id. Can be sent at any time
+        #[serde(rename = "update_hit")]
+        UpdateHit(Hit),
+
+        /// Removes shown hit. Can be sent at any time
+        #[serde(rename = "remove_hit")]
+        RemoveHit(HitId),
+
+        /// Sets plugin status shown under results. `None` hides it. Can be sent at any time
+        #[serde(rename = "status")]
+        Status(Option<Status>),
+
+        /// Optional. Without it plugin is named after its process
+        #[serde(rename = "register")]
+        Register(PluginInfo),
+    }
+
+    impl Payload {
+        /// Id of the package this payload answers to
+        pub fn reply_to(&self) -> Option<&PackageId> {
+            match self {
+                Self::Result((id, _)) | Self::Action((id, _)) | Self::Preview((id, _)) => Some(id),
+                _ => None,
+            }
+        }
     }
 
     #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
     pub struct Package {
         id: PackageId,
 
@@ -100,13 +362,132 @@ mod hit {
     #[doc(alias = "Uuid")]
     pub type HitId = Uuid;
 
+    /// Plain string is accepted too, as plugins sent it before:
+    /// absolute path, URI or icon name, see [`Icon::from`]
+    #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash, glib::Boxed)]
+    #[serde(remote = "Self")]
+    #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+    #[boxed_type(name = "UnirunIcon", nullable)]
+    pub enum Icon {
+        /// Icon name from current icon theme
+        #[serde(rename = "name")]
+        Name(String),
+
+        #[serde(rename = "path")]
+        Path(String),
+
+        #[serde(rename = "uri")]
+        Uri(String),
+
+        /// Base64 encoded image. Keep it small as whole package must fit socket buffer
+        #[serde(rename = "bytes")]
+        Bytes { mime_type: String, data: String },
+
+        /// Serialized `GIcon` (`g_icon_to_string`)
+        #[serde(rename = "gicon")]
+        GIcon(String),
+    }
+
+    impl Serialize for Icon {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            Icon::serialize(self, serializer)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Icon {
+        fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            #[derive(Deserialize)]
+            #[serde(untagged)]
+            enum Wire {
+                Plain(String),
+                Tagged(#[serde(with = "Icon")] Icon),
+            }
+
+            Ok(match Wire::deserialize(deserializer)? {
+                Wire::Plain(icon) => Icon::from(icon.as_str()),
+                Wire::Tagged(icon) => icon,
+            })
+        }
+    }
+
+    impl Icon {
+        pub fn from_bytes(mime_type: &str, data: &[u8]) -> Self {
+            Self::Bytes {
+                mime_type: mime_type.to_owned(),
+                data: glib::base64_encode(data).to_string(),
+            }
+        }
+    }
+
+    /// Absolute path, URI or icon name
+    impl From<&str> for Icon {
+        fn from(value: &str) -> Self {
+            if std::path::Path::new(value).is_absolute() {
+                Self::Path(value.to_owned())
+            } else if glib::Uri::peek_scheme(value).is_some() {
+                Self::Uri(value.to_owned())
+            } else {
+                Self::Name(value.to_owned())
+            }
+        }
+    }
+
+    /// Content shown in runner preview pane for selected hit
+    #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, glib::Boxed)]
+    #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+    #[boxed_type(name = "UnirunPreview", nullable)]
+    pub enum Preview {
+        #[serde(rename = "text")]
+        Text {
+            text: String,
+            #[serde(default)]
+            use_pango: bool,
+        },
+
+        /// Path to image file
+        #[serde(rename = "image")]
+        Image(String),
+
+        /// Path to file whose first lines are shown
+        #[serde(rename = "file")]
+        File(String),
+
+        /// Key-value pairs
+        #[serde(rename = "metadata")]
+        Metadata(Vec<(String, String)>),
+    }
+
+    /// Matched char ranges `[start, end)` of `title` and `description` text (without markup).
+    /// Runner computes them by itself if plugin doesn't provide
+    #[derive(Default, Debug, Serialize, Deserialize, Clone, PartialEq, Eq, glib::Boxed)]
+    #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+    #[boxed_type(name = "UnirunHighlight", nullable)]
+    pub struct Highlight {
+        #[serde(default)]
+        pub title: Vec<(usize, usize)>,
+        #[serde(default)]
+        pub description: Vec<(usize, usize)>,
+    }
+
     #[derive(Default, Debug, Serialize, Deserialize, Clone, PartialEq)]
+    #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
     pub struct Hit {
         pub id: HitId,
         pub title: String,
         pub description: Option<String>,
-        pub icon: Option<String>,
+        pub icon: Option<Icon>,
         pub use_pango: bool,
+        /// Text copied by runner instead of `title`
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub copy: Option<String>,
+        /// Preview embedded into hit. If missing runner asks for it with [`crate::package::Command::GetPreview`]
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub preview: Option<Preview>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub highlight: Option<Highlight>,
+        /// Runner must not reuse answer with this hit for the same query (clock, calculator, ...)
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        pub no_cache: bool,
     }
 
     impl Hit {
@@ -120,8 +501,12 @@ mod hit {
                 id: HitId::new(),
                 title: title.to_owned(),
                 description: description.map(str::to_owned),
-                icon: icon.map(str::to_owned),
+                icon: icon.map(Icon::from),
                 use_pango,
+                copy: None,
+                preview: None,
+                highlight: None,
+                no_cache: false,
             }
         }
     }
@@ -0,0 +1,105 @@
+//! Log of packages read and written by [`crate::socket::Stream`], one JSON object per line.
+//!
+//! Runner starts it with `--record <file.jsonl>`, `unirun-replay` plays it back
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, LineWriter, Write},
+    path::Path,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex, MutexGuard, PoisonError,
+    },
+    time::Instant,
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// Read from the stream
+    #[serde(rename = "in")]
+    In,
+
+    /// Written to the stream
+    #[serde(rename = "out")]
+    Out,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct Entry {
+    /// Milliseconds since recording started
+    pub time: u64,
+    /// Stream package went through, unique within recording
+    pub connection: u64,
+    /// Process on the other side of the stream
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pid: Option<u32>,
+    pub direction: Direction,
+    /// Package as it was sent. Frame that is not JSON is kept as string
+    pub package: Value,
+}
+
+struct Recorder {
+    started: Instant,
+    file: LineWriter<File>,
+}
+
+static RECORDER: Mutex<Option<Recorder>> = Mutex::new(None);
+static CONNECTIONS: AtomicU64 = AtomicU64::new(0);
+
+fn recorder() -> MutexGuard<'static, Option<Recorder>> {
+    RECORDER.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Starts writing every package to `path`, file is truncated
+pub fn start(path: &Path) -> io::Result<()> {
+    let file = LineWriter::new(File::create(path)?);
+    recorder().replace(Recorder {
+        started: Instant::now(),
+        file,
+    });
+    Ok(())
+}
+
+pub(crate) fn next_connection() -> u64 {
+    CONNECTIONS.fetch_add(1, Ordering::Relaxed)
+}
+
+pub(crate) fn log(connection: u64, pid: Option<u32>, direction: Direction, json: &str) {
+    let mut recorder = recorder();
+    let Some(recorder) = recorder.as_mut() else {
+        return;
+    };
+
+    let entry = Entry {
+        time: recorder.started.elapsed().as_millis() as u64,
+        connection,
+        pid,
+        direction,
+        package: serde_json::from_str(json).unwrap_or_else(|_| Value::String(json.to_owned())),
+    };
+    // recording must not break the session
+    if let Ok(line) = serde_json::to_string(&entry) {
+        let _ = writeln!(recorder.file, "{}", line);
+    }
+}
+
+/// Entries of recording in order they were written
+pub fn read(path: &Path) -> io::Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+    for (index, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry = serde_json::from_str(&line).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("line {}: {}", index + 1, e),
+            )
+        })?;
+        entries.push(entry);
+    }
+    Ok(entries)
+}
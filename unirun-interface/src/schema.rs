@@ -0,0 +1,67 @@
+//! JSON Schema of messages, for plugins and tools written in other languages.
+//!
+//! Generated files are kept in `unirun-interface/schema`, regenerate them with
+//! `cargo run -p unirun-interface --features schema --bin unirun-schema -- unirun-interface/schema`
+use schemars::{schema_for, Schema};
+use serde_json::{json, Value};
+
+use crate::{
+    control::{Request, Response},
+    package::Package,
+};
+
+/// File name and schema of every message type. Plugin package goes first
+pub fn all() -> Vec<(&'static str, Schema)> {
+    vec![
+        ("package.schema.json", package()),
+        ("control-request.schema.json", schema_for!(Request)),
+        ("control-response.schema.json", schema_for!(Response)),
+    ]
+}
+
+/// Pretty JSON with trailing newline, as files are written
+pub fn to_string(schema: &Schema) -> String {
+    let mut json = serde_json::to_string_pretty(schema).expect("schema is serializable");
+    json.push('\n');
+    json
+}
+
+/// Unit variants of [`crate::package::Payload`] are flattened into package by serde as
+/// `"clear_items": null`, schemars describes them as bare strings
+fn package() -> Schema {
+    let mut schema = schema_for!(Package);
+    if let Some(Value::Array(variants)) = schema.get_mut("oneOf") {
+        for variant in variants {
+            let Some(Value::String(name)) = variant.get("const").cloned() else {
+                continue;
+            };
+            let description = variant.get("description").cloned();
+            *variant = json!({
+                "type": "object",
+                "properties": { name.clone(): { "type": "null" } },
+                "required": [name],
+            });
+            if let Some(description) = description {
+                variant["description"] = description;
+            }
+        }
+    }
+    accept_string(&mut schema, "Icon");
+    accept_string(&mut schema, "Query");
+    schema
+}
+
+/// Types deserialized from plain string as well, schemars knows only about derived form
+fn accept_string(schema: &mut Schema, name: &str) {
+    let Some(definition) = schema.pointer_mut(&format!("/$defs/{}", name)) else {
+        return;
+    };
+    let mut derived = definition.take();
+    let description = derived
+        .as_object_mut()
+        .and_then(|derived| derived.remove("description"));
+    *definition = json!({ "anyOf": [{ "type": "string" }, derived] });
+    if let Some(description) = description {
+        definition["description"] = description;
+    }
+}
@@ -0,0 +1,87 @@
+//! Packages in the shape older runner and plugins send them
+
+use serde_json::json;
+use unirun_interface::package::{Command, Icon, Package, Payload, Query};
+
+fn parse(json: serde_json::Value) -> Payload {
+    serde_json::from_value::<Package>(json).unwrap().payload
+}
+
+#[test]
+fn plain_string_icon() {
+    let payload = parse(json!({
+        "id": "1",
+        "hit": {
+            "id": "2",
+            "title": "Firefox",
+            "description": "Web browser",
+            "icon": "firefox",
+            "use_pango": false
+        }
+    }));
+    let Payload::Hit(hit) = payload else {
+        panic!("Not a hit: {:?}", payload);
+    };
+    assert_eq!(hit.title, "Firefox");
+    assert_eq!(hit.icon, Some(Icon::Name("firefox".to_owned())));
+
+    let payload = parse(json!({
+        "id": "1",
+        "register": { "name": "files", "icon": "/usr/share/icons/files.svg" }
+    }));
+    let Payload::Register(info) = payload else {
+        panic!("Not a register: {:?}", payload);
+    };
+    assert_eq!(
+        info.icon,
+        Some(Icon::Path("/usr/share/icons/files.svg".to_owned()))
+    );
+}
+
+#[test]
+fn tagged_icon() {
+    let icon = serde_json::from_value::<Icon>(json!({ "uri": "file:///tmp/a.png" })).unwrap();
+    assert_eq!(icon, Icon::Uri("file:///tmp/a.png".to_owned()));
+    assert_eq!(
+        serde_json::to_value(&icon).unwrap(),
+        json!({ "uri": "file:///tmp/a.png" })
+    );
+    assert!(serde_json::from_value::<Icon>(json!({ "unknown": "a" })).is_err());
+}
+
+#[test]
+fn plain_string_query() {
+    let payload = parse(json!({ "id": "1", "command": { "get_data": "fire" } }));
+    assert_eq!(
+        payload,
+        Payload::Command(Command::GetData(Query::from("fire")))
+    );
+
+    let payload = parse(json!({
+        "id": "1",
+        "command": { "get_data": { "text": "fire", "limit": 50 } }
+    }));
+    let Payload::Command(Command::GetData(query)) = payload else {
+        panic!("Not a query: {:?}", payload);
+    };
+    assert_eq!(query.text, "fire");
+    assert_eq!(query.limit, Some(50));
+}
+
+#[test]
+fn text_only_query_is_sent_as_string() {
+    let package = serde_json::to_value(Package::new(Payload::Command(Command::GetData(
+        Query::from("fire"),
+    ))))
+    .unwrap();
+    assert_eq!(package["command"], json!({ "get_data": "fire" }));
+
+    let query = Query {
+        limit: Some(50),
+        ..Query::from("fire")
+    };
+    assert_eq!(
+        serde_json::to_value(&query).unwrap(),
+        json!({ "text": "fire", "limit": 50 })
+    );
+}
@@ -0,0 +1,284 @@
+use std::{fs, path::Path};
+
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::json;
+use unirun_interface::{
+    control::{PluginEntry, PluginStats, Request, Response, RunnerStatus, Timing},
+    package::{
+        Action, ClipboardContent, Command, Highlight, Hit, HitId, Icon, Package, PackageId,
+        Payload, PluginInfo, Preview, Progress, Query, Severity, Status,
+    },
+    schema,
+};
+
+fn validator(file_name: &str) -> jsonschema::Validator {
+    let (_, schema) = schema::all()
+        .into_iter()
+        .find(|(name, _)| *name == file_name)
+        .expect("No such schema");
+    jsonschema::validator_for(schema.as_value()).expect("Schema is invalid")
+}
+
+/// Serialized `value` matches schema and deserializes back to the same value
+fn assert_round_trip<T>(validator: &jsonschema::Validator, value: &T)
+where
+    T: Serialize + DeserializeOwned + PartialEq + std::fmt::Debug,
+{
+    let json = serde_json::to_value(value).unwrap();
+    let errors = validator
+        .iter_errors(&json)
+        .map(|e| e.to_string())
+        .collect::<Vec<_>>();
+    assert!(
+        errors.is_empty(),
+        "{} doesn't match schema: {:?}",
+        json,
+        errors
+    );
+
+    let back = serde_json::from_value::<T>(json).unwrap();
+    assert_eq!(&back, value);
+}
+
+fn hit() -> Hit {
+    let mut hit = Hit::new("<b>Firefox</b>", Some("Web browser"), Some("firefox"), true);
+    hit.copy = Some("firefox".to_owned());
+    hit.preview = Some(Preview::Metadata(vec![(
+        "Exec".to_owned(),
+        "firefox".to_owned(),
+    )]));
+    hit.highlight = Some(Highlight {
+        title: vec![(0, 4)],
+        description: vec![],
+    });
+    hit.no_cache = true;
+    hit
+}
+
+fn payloads() -> Vec<Payload> {
+    let id = PackageId::new();
+    let mut status = Status::new(Severity::Warning, "Indexing");
+    status.progress = Some(Progress { done: 1, total: 3 });
+
+    let mut payloads = vec![
+        Payload::Command(Command::Quit),
+        Payload::Command(Command::Abort),
+        Payload::Command(Command::Activate(HitId::new())),
+        Payload::Command(Command::GetPreview(HitId::new())),
+        Payload::Command(Command::Configure(serde_json::Map::new())),
+        Payload::Command(Command::Configure(
+            json!({ "engine": "duckduckgo", "results": [1, 2], "proxy": { "port": 8080 } })
+                .as_object()
+                .unwrap()
+                .clone(),
+        )),
+        Payload::Command(Command::GetData(Query::from("fire"))),
+        Payload::Command(Command::GetData(Query {
+            text: "fire".to_owned(),
+            context: Some("bookmarks".to_owned()),
+            limit: Some(50),
+            offset: 50,
+        })),
+        Payload::Result((id.clone(), Ok(()))),
+        Payload::Result((id.clone(), Err("No such hit".to_owned()))),
+        Payload::Hit(hit()),
+        Payload::Hit(Hit::new("plain", None, None, false)),
+        Payload::Item(hit()),
+        Payload::ClearItems,
+        Payload::UpdateHit(hit()),
+        Payload::RemoveHit(HitId::new()),
+        Payload::Status(Some(status)),
+        Payload::Status(None),
+        Payload::Register(PluginInfo::new("applications", Some("system-run"))),
+        Payload::Register(PluginInfo {
+            name: "websearch".to_owned(),
+            icon: None,
+            fallback: true,
+            paging: true,
+            preview: true,
+        }),
+        Payload::Preview((id.clone(), None)),
+        Payload::Preview((id.clone(), Some(Preview::Image("/tmp/a.png".to_owned())))),
+        Payload::Preview((
+            id.clone(),
+            Some(Preview::Text {
+                text: "text".to_owned(),
+                use_pango: false,
+            }),
+        )),
+        Payload::Preview((id.clone(), Some(Preview::File("/etc/hosts".to_owned())))),
+    ];
+
+    let actions = [
+        Action::Close,
+        Action::KeepOpen,
+        Action::SetQuery("new".to_owned()),
+        Action::Clipboard {
+            content: ClipboardContent::Text("text".to_owned()),
+            close: false,
+        },
+        Action::Clipboard {
+            content: ClipboardContent::Text("text".to_owned()),
+            close: true,
+        },
+        Action::Clipboard {
+            content: ClipboardContent::File {
+                path: "/tmp/a.png".to_owned(),
+                mime_type: None,
+            },
+            close: false,
+        },
+        Action::Clipboard {
+            content: ClipboardContent::Data {
+                mime_type: "text/uri-list".to_owned(),
+                data: "file:///tmp/a.png".to_owned(),
+            },
+            close: false,
+        },
+        Action::Message("Done".to_owned()),
+        Action::Refresh,
+        Action::Push {
+            context: "folder".to_owned(),
+            title: "Folder".to_owned(),
+        },
+    ];
+    payloads.extend(
+        actions
+            .into_iter()
+            .map(|action| Payload::Action((id.clone(), action))),
+    );
+
+    let icons = [
+        Icon::Name("firefox".to_owned()),
+        Icon::Path("/tmp/a.png".to_owned()),
+        Icon::Uri("file:///tmp/a.png".to_owned()),
+        Icon::Bytes {
+            mime_type: "image/png".to_owned(),
+            data: "iVBORw0KGgo=".to_owned(),
+        },
+        Icon::GIcon(". GThemedIcon firefox".to_owned()),
+    ];
+    payloads.extend(icons.into_iter().map(|icon| {
+        let mut hit = Hit::new("icon", None, None, false);
+        hit.icon = Some(icon);
+        Payload::Hit(hit)
+    }));
+
+    payloads
+}
+
+#[test]
+fn packages_match_schema() {
+    let validator = validator("package.schema.json");
+    for payload in payloads() {
+        assert_round_trip(&validator, &Package::new(payload));
+    }
+}
+
+#[test]
+fn control_messages_match_schema() {
+    let requests_validator = validator("control-request.schema.json");
+    let requests = [
+        Request::Show,
+        Request::Hide,
+        Request::Toggle,
+        Request::Query("fire".to_owned()),
+        Request::ReloadConfig,
+        Request::ReloadPlugins,
+        Request::ListPlugins,
+        Request::RestartPlugin("applications".to_owned()),
+        Request::Status,
+        Request::Stats,
+    ];
+    for request in &requests {
+        assert_round_trip(&requests_validator, request);
+    }
+
+    let responses_validator = validator("control-response.schema.json");
+    let responses = [
+        Response::Ok,
+        Response::Error("No such plugin".to_owned()),
+        Response::Plugins(vec![PluginEntry {
+            pid: 42,
+            name: "applications".to_owned(),
+            fallback: false,
+            busy: true,
+            status: Some(Status::new(Severity::Info, "Ready")),
+        }]),
+        Response::Status(RunnerStatus {
+            visible: true,
+            query: "fire".to_owned(),
+            hits: 3,
+            plugins: 2,
+            views: vec!["Bookmarks".to_owned()],
+        }),
+        Response::Stats(vec![PluginStats {
+            name: "applications".to_owned(),
+            queries: 12,
+            aborted: 3,
+            hits_per_query: 4.5,
+            first_hit: Timing {
+                count: 10,
+                average_ms: 2.5,
+                max_ms: 7.0,
+            },
+            completion: Timing {
+                count: 12,
+                average_ms: 3.25,
+                max_ms: 9.0,
+            },
+            activation: Timing {
+                count: 0,
+                average_ms: 0.0,
+                max_ms: 0.0,
+            },
+            errors: 1,
+            restarts: 0,
+        }]),
+    ];
+    for response in &responses {
+        assert_round_trip(&responses_validator, response);
+    }
+}
+
+#[test]
+fn schema_rejects_wrong_shape() {
+    let validator = validator("package.schema.json");
+    let invalid = [
+        // missing id
+        json!({ "command": "quit" }),
+        // `Result` is `{"Ok": null}` or `{"Err": "message"}`
+        json!({ "id": "1", "result": ["2", "ok"] }),
+        json!({ "id": "1", "command": { "get_data": 5 } }),
+        json!({ "id": "1", "command": { "get_data": { "limit": 5 } } }),
+        json!({ "id": "1", "hit": { "title": "no id" } }),
+        json!({ "id": "1", "unknown": null }),
+    ];
+    for json in invalid {
+        assert!(!validator.is_valid(&json), "{} matches schema", json);
+    }
+
+    let valid = [
+        json!({ "id": "1", "result": ["2", { "Err": "No such hit" }] }),
+        // query and icon as plain strings, as older runner and plugins send them
+        json!({ "id": "1", "command": { "get_data": "text" } }),
+        json!({ "id": "1", "hit": { "id": "2", "title": "Firefox", "icon": "firefox", "use_pango": false } }),
+    ];
+    for json in valid {
+        assert!(validator.is_valid(&json), "{} doesn't match schema", json);
+    }
+}
+
+/// Files in `unirun-interface/schema` are what plugins in other languages use
+#[test]
+fn schema_files_are_up_to_date() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("schema");
+    for (file_name, schema) in schema::all() {
+        let file = fs::read_to_string(dir.join(file_name)).unwrap_or_default();
+        assert!(
+            file == schema::to_string(&schema),
+            "{} is outdated, regenerate it with unirun-schema binary",
+            file_name
+        );
+    }
+}
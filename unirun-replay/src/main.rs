@@ -0,0 +1,252 @@
+mod session;
+
+use std::{
+    collections::BTreeMap,
+    env,
+    io::{self, ErrorKind},
+    os::unix::net::{UnixListener, UnixStream},
+    path::PathBuf,
+    process::{Child, Command, ExitCode, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use serde_json::Value;
+use session::{Options, Session};
+use unirun_if::{
+    path,
+    record::{self, Direction, Entry},
+};
+
+const USAGE: &str = "\
+Usage: unirun-replay [options] list <recording.jsonl>
+       unirun-replay [options] plugin <recording.jsonl> <connection>
+       unirun-replay [options] runner <recording.jsonl> <connection> <plugin> [args...]
+
+Plays back connection recorded with `unirun --record <recording.jsonl>`
+
+Commands:
+    list    print recorded connections
+    plugin  acts as recorded plugin and connects to running unirun
+    runner  acts as unirun, launches plugin in temporary runtime dir
+
+Packages of the other side are expected in recorded order, only packages that came
+in a row may come in any order. IDs the other side generates are mapped to recorded ones
+
+Options:
+    --timeout <ms>  wait for every expected package, 3000 by default
+    --realtime      keep recorded pauses between packages
+    -h, --help      print this help";
+
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+struct Args {
+    options: Options,
+    command: String,
+    file: PathBuf,
+    rest: Vec<String>,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Option<Args> {
+    let mut options = Options {
+        timeout: Duration::from_secs(3),
+        realtime: false,
+    };
+    loop {
+        let arg = args.next()?;
+        match arg.as_str() {
+            "--timeout" => options.timeout = Duration::from_millis(args.next()?.parse().ok()?),
+            "--realtime" => options.realtime = true,
+            _ if arg.starts_with('-') => return None,
+            _ => {
+                return Some(Args {
+                    options,
+                    command: arg,
+                    file: args.next()?.into(),
+                    rest: args.collect(),
+                })
+            }
+        }
+    }
+}
+
+fn list(entries: &[Entry]) {
+    #[derive(Default)]
+    struct Connection {
+        pid: Option<u32>,
+        name: Option<String>,
+        received: usize,
+        sent: usize,
+        last: u64,
+    }
+
+    let mut connections = BTreeMap::<u64, Connection>::new();
+    for entry in entries {
+        let connection = connections.entry(entry.connection).or_default();
+        connection.pid = connection.pid.or(entry.pid);
+        connection.last = entry.time;
+        match entry.direction {
+            Direction::In => connection.received += 1,
+            Direction::Out => connection.sent += 1,
+        }
+        if let Some(name) = entry
+            .package
+            .pointer("/register/name")
+            .and_then(Value::as_str)
+        {
+            connection.name = Some(name.to_owned());
+        }
+    }
+
+    println!("connection  pid      plugin          in    out   last ms");
+    for (id, connection) in connections {
+        println!(
+            "{:<12}{:<9}{:<16}{:<6}{:<6}{}",
+            id,
+            connection
+                .pid
+                .map(|pid| pid.to_string())
+                .unwrap_or_default(),
+            connection.name.as_deref().unwrap_or("-"),
+            connection.received,
+            connection.sent,
+            connection.last
+        );
+    }
+}
+
+/// Plugin process, killed on drop if it didn't exit after replay
+struct Plugin(Child);
+
+impl Plugin {
+    fn launch(
+        command: &mut Command,
+        listener: &UnixListener,
+        timeout: Duration,
+    ) -> io::Result<(Self, UnixStream)> {
+        let mut plugin = Self(command.stdin(Stdio::null()).spawn()?);
+
+        let deadline = Instant::now() + timeout;
+        loop {
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    stream.set_nonblocking(false)?;
+                    return Ok((plugin, stream));
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => {}
+                Err(e) => return Err(e),
+            }
+            if let Some(status) = plugin.0.try_wait()? {
+                return Err(io::Error::other(format!(
+                    "plugin exited before connecting, {}",
+                    status
+                )));
+            }
+            if Instant::now() >= deadline {
+                return Err(io::Error::other(format!(
+                    "plugin didn't connect within {:?}",
+                    timeout
+                )));
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+}
+
+impl Drop for Plugin {
+    fn drop(&mut self) {
+        if let Ok(None) = self.0.try_wait() {
+            let _ = self.0.kill();
+            let _ = self.0.wait();
+        }
+    }
+}
+
+fn run(args: Args) -> io::Result<ExitCode> {
+    let entries = record::read(&args.file)?;
+    if args.command == "list" {
+        list(&entries);
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let Some(connection) = args.rest.first().and_then(|c| c.parse::<u64>().ok()) else {
+        eprintln!("{}", USAGE);
+        return Ok(ExitCode::FAILURE);
+    };
+    let entries = entries
+        .iter()
+        .filter(|entry| entry.connection == connection)
+        .collect::<Vec<_>>();
+    if entries.is_empty() {
+        return Err(io::Error::other(format!(
+            "no connection {} in recording",
+            connection
+        )));
+    }
+
+    // recording is made by runner, `in` is what plugin sent
+    let (_plugin, _runtime, stream, send) = match args.command.as_str() {
+        "plugin" => (
+            None,
+            None,
+            UnixStream::connect(path::socket())?,
+            Direction::In,
+        ),
+        "runner" => {
+            let Some(program) = args.rest.get(1) else {
+                eprintln!("{}", USAGE);
+                return Ok(ExitCode::FAILURE);
+            };
+
+            // running unirun must not get the plugin, plugin inherits it
+            let runtime = tempfile::Builder::new()
+                .prefix("unirun-replay-")
+                .tempdir()?;
+            env::set_var("XDG_RUNTIME_DIR", runtime.path());
+            let listener = UnixListener::bind(path::socket())?;
+            listener.set_nonblocking(true)?;
+
+            let mut command = Command::new(program);
+            command.args(&args.rest[2..]);
+            let (plugin, stream) = Plugin::launch(&mut command, &listener, args.options.timeout)?;
+            (Some(plugin), Some(runtime), stream, Direction::Out)
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            return Ok(ExitCode::FAILURE);
+        }
+    };
+
+    let mismatches = Session::new(stream, args.options)?.play(&entries, send)?;
+    if mismatches == 0 {
+        println!("replayed {} packages", entries.len());
+        Ok(ExitCode::SUCCESS)
+    } else {
+        println!("{} mismatches", mismatches);
+        Ok(ExitCode::FAILURE)
+    }
+}
+
+fn main() -> ExitCode {
+    let args = match env::args().nth(1).as_deref() {
+        Some("-h" | "--help") => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        _ => match parse_args(env::args().skip(1)) {
+            Some(args) => args,
+            None => {
+                eprintln!("{}", USAGE);
+                return ExitCode::FAILURE;
+            }
+        },
+    };
+
+    match run(args) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("Failed to replay: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
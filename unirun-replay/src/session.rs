@@ -0,0 +1,284 @@
+use std::{
+    collections::HashMap,
+    io::{self, ErrorKind},
+    os::unix::net::UnixStream,
+    thread,
+    time::Duration,
+};
+
+use serde_json::Value;
+use unirun_if::{
+    record::{Direction, Entry},
+    socket::{read_frame, write_frame},
+};
+
+pub struct Options {
+    /// Wait for every expected package
+    pub timeout: Duration,
+    /// Keep recorded pauses before sent packages
+    pub realtime: bool,
+}
+
+/// Plays one side of recorded connection
+pub struct Session {
+    stream: UnixStream,
+    options: Options,
+    /// Recorded IDs to IDs the other side generated this time
+    ids: HashMap<String, String>,
+    mismatches: usize,
+}
+
+/// Payload name with command or action name, so expected packages are matched by it
+fn kind(package: &Value) -> String {
+    let Value::Object(object) = package else {
+        return "malformed".to_owned();
+    };
+    let Some((key, value)) = object.iter().find(|(key, _)| key.as_str() != "id") else {
+        return "empty".to_owned();
+    };
+    match value {
+        Value::String(name) => format!("{}:{}", key, name),
+        Value::Object(object) if object.len() == 1 => {
+            format!("{}:{}", key, object.keys().next().unwrap())
+        }
+        _ => key.clone(),
+    }
+}
+
+/// Frame that is not JSON is kept as string, as recording does
+fn parse_frame(frame: String) -> Value {
+    serde_json::from_str(&frame).unwrap_or(Value::String(frame))
+}
+
+fn is_uuid(text: &str) -> bool {
+    text.len() == 36
+        && text.char_indices().all(|(index, c)| match index {
+            8 | 13 | 18 | 23 => c == '-',
+            _ => c.is_ascii_hexdigit(),
+        })
+}
+
+/// Remembers UUIDs that differ between recorded and actual package
+fn learn(recorded: &Value, actual: &Value, ids: &mut HashMap<String, String>) {
+    match (recorded, actual) {
+        (Value::String(recorded), Value::String(actual))
+            if recorded != actual && is_uuid(recorded) && is_uuid(actual) =>
+        {
+            ids.insert(recorded.clone(), actual.clone());
+        }
+        (Value::Array(recorded), Value::Array(actual)) => {
+            for (recorded, actual) in recorded.iter().zip(actual) {
+                learn(recorded, actual, ids);
+            }
+        }
+        (Value::Object(recorded), Value::Object(actual)) => {
+            for (key, recorded) in recorded {
+                if let Some(actual) = actual.get(key) {
+                    learn(recorded, actual, ids);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Recorded package with IDs of this session
+fn rewrite(package: &Value, ids: &HashMap<String, String>) -> Value {
+    match package {
+        Value::String(text) => Value::String(ids.get(text).unwrap_or(text).clone()),
+        Value::Array(items) => Value::Array(items.iter().map(|item| rewrite(item, ids)).collect()),
+        Value::Object(object) => Value::Object(
+            object
+                .iter()
+                .map(|(key, value)| (key.clone(), rewrite(value, ids)))
+                .collect(),
+        ),
+        value => value.clone(),
+    }
+}
+
+impl Session {
+    pub fn new(stream: UnixStream, options: Options) -> io::Result<Self> {
+        stream.set_read_timeout(Some(options.timeout))?;
+        Ok(Self {
+            stream,
+            options,
+            ids: HashMap::new(),
+            mismatches: 0,
+        })
+    }
+
+    fn mismatch(&mut self, reason: &str, package: &Value) {
+        self.mismatches += 1;
+        println!("! {}: {}", reason, package);
+    }
+
+    fn send(&mut self, package: &Value) -> io::Result<()> {
+        let package = rewrite(package, &self.ids);
+        // malformed frames are replayed as they were
+        let data = match &package {
+            Value::String(raw) => raw.clone(),
+            package => package.to_string(),
+        };
+        write_frame(&mut self.stream, data.as_bytes())?;
+        println!("> {}", data);
+        Ok(())
+    }
+
+    /// Reads packages until all `expected` came, in any order.
+    /// Returns `false` if the other side closed connection
+    fn receive(&mut self, mut expected: Vec<&Value>) -> io::Result<bool> {
+        while !expected.is_empty() {
+            let frame = match read_frame(&mut self.stream) {
+                Ok(frame) => frame,
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                    for package in expected {
+                        self.mismatch("missing", package);
+                    }
+                    return Ok(true);
+                }
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => {
+                    println!("connection closed");
+                    for package in expected {
+                        self.mismatch("missing", package);
+                    }
+                    return Ok(false);
+                }
+                Err(e) => return Err(e),
+            };
+            println!("< {}", frame);
+
+            let actual = parse_frame(frame);
+            let Some(position) = expected.iter().position(|p| kind(p) == kind(&actual)) else {
+                self.mismatch("unexpected", &actual);
+                continue;
+            };
+
+            let recorded = expected.remove(position);
+            learn(recorded, &actual, &mut self.ids);
+            let recorded = rewrite(recorded, &self.ids);
+            if recorded != actual {
+                self.mismatch("differs from recorded", &recorded);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Sends `entries` going in `send` direction and checks the other ones come back.
+    /// Returns number of mismatches
+    pub fn play(mut self, entries: &[&Entry], send: Direction) -> io::Result<usize> {
+        let mut index = 0;
+        while index < entries.len() {
+            let entry = entries[index];
+            if entry.direction == send {
+                if self.options.realtime && index > 0 {
+                    let pause = entry.time.saturating_sub(entries[index - 1].time);
+                    thread::sleep(Duration::from_millis(pause));
+                }
+                self.send(&entry.package)?;
+                index += 1;
+                continue;
+            }
+
+            let end = entries[index..]
+                .iter()
+                .position(|entry| entry.direction == send)
+                .map_or(entries.len(), |position| index + position);
+            let expected = entries[index..end]
+                .iter()
+                .map(|entry| &entry.package)
+                .collect();
+            if !self.receive(expected)? {
+                for entry in entries[end..].iter().filter(|e| e.direction != send) {
+                    self.mismatch("missing", &entry.package);
+                }
+                break;
+            }
+            index = end;
+        }
+        Ok(self.mismatches)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    const RECORDED: &str = "11111111-1111-4111-8111-111111111111";
+    const ACTUAL: &str = "22222222-2222-4222-8222-222222222222";
+
+    #[test]
+    fn kind_of_package() {
+        assert_eq!(
+            kind(&json!({ "id": "1", "command": "quit" })),
+            "command:quit"
+        );
+        assert_eq!(
+            kind(&json!({ "id": "1", "command": { "get_data": { "text": "a" } } })),
+            "command:get_data"
+        );
+        assert_eq!(
+            kind(&json!({ "id": "1", "result": ["2", { "Ok": null }] })),
+            "result"
+        );
+        assert_eq!(kind(&json!({ "id": "1" })), "empty");
+    }
+
+    #[test]
+    fn uuids() {
+        assert!(is_uuid(RECORDED));
+        assert!(!is_uuid("11111111-1111-4111-8111-11111111111"));
+        assert!(!is_uuid("11111111_1111-4111-8111-111111111111"));
+        assert!(!is_uuid("1111111g-1111-4111-8111-111111111111"));
+    }
+
+    #[test]
+    fn runner_id_in_result_is_remapped() {
+        // runner answered hit with its own package id, plugin refers to it later
+        let recorded = json!({ "id": "a", "result": [RECORDED, { "Ok": null }] });
+        let actual = json!({ "id": "a", "result": [ACTUAL, { "Ok": null }] });
+        assert_eq!(kind(&recorded), kind(&actual));
+
+        let mut ids = HashMap::new();
+        learn(&recorded, &actual, &mut ids);
+        assert_eq!(ids.get(RECORDED).map(String::as_str), Some(ACTUAL));
+        assert_eq!(rewrite(&recorded, &ids), actual);
+
+        let next = json!({ "id": "b", "command": { "activate": RECORDED } });
+        assert_eq!(
+            rewrite(&next, &ids),
+            json!({ "id": "b", "command": { "activate": ACTUAL } })
+        );
+    }
+
+    #[test]
+    fn only_differing_uuids_are_learned() {
+        let mut ids = HashMap::new();
+        learn(
+            &json!({ "id": RECORDED, "hit": { "title": "old" } }),
+            &json!({ "id": RECORDED, "hit": { "title": "new" } }),
+            &mut ids,
+        );
+        learn(&json!(["not-a-uuid"]), &json!(["other"]), &mut ids);
+        assert!(ids.is_empty());
+    }
+
+    #[test]
+    fn malformed_frame_is_matched_as_is() {
+        let recorded = Value::String("{\"id\": broken".to_owned());
+        let actual = parse_frame("{\"id\": broken".to_owned());
+        assert_eq!(kind(&recorded), "malformed");
+        assert_eq!(kind(&actual), kind(&recorded));
+
+        let ids = HashMap::from([(RECORDED.to_owned(), ACTUAL.to_owned())]);
+        assert_eq!(rewrite(&recorded, &ids), actual);
+        assert_ne!(
+            kind(&parse_frame(
+                "{\"id\": \"1\", \"command\": \"abort\"}".to_owned()
+            )),
+            kind(&recorded)
+        );
+    }
+}
@@ -0,0 +1,234 @@
+//! Plugin following [`MockScript`] from `UNIRUN_MOCK_SCRIPT`.
+//!
+//! Talks to the socket with plain std I/O, so what it sends doesn't depend on code under test.
+//! Settings from `Configure` are shown back as its status
+
+use std::{
+    collections::VecDeque,
+    env,
+    io::{self, Read, Write},
+    os::unix::net::UnixStream,
+    process::ExitCode,
+    sync::mpsc::{self, Receiver, RecvTimeoutError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use unirun_if::{
+    constants::SOCKET_BUFFER_SIZE,
+    package::{Command, HitId, Package, PackageId, Payload, PluginInfo, Query, Severity, Status},
+    path,
+};
+use unirun_test_support::{MockScript, OnQuery, SCRIPT_ENV};
+
+fn read_package(stream: &mut UnixStream) -> io::Result<Package> {
+    let mut buffer = [0; SOCKET_BUFFER_SIZE];
+    stream.read_exact(&mut buffer)?;
+    let json = String::from_utf8_lossy(&buffer);
+    serde_json::from_str(json.trim_end_matches(char::from(0)))
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn write_frame(stream: &mut UnixStream, data: &[u8]) -> io::Result<()> {
+    let mut buffer = [0; SOCKET_BUFFER_SIZE];
+    let len = data.len().min(SOCKET_BUFFER_SIZE);
+    buffer[..len].copy_from_slice(&data[..len]);
+    stream.write_all(&buffer)
+}
+
+struct Mock {
+    script: MockScript,
+    stream: UnixStream,
+    incoming: Receiver<Package>,
+    /// Commands that came while hits were sent
+    pending: VecDeque<Package>,
+}
+
+/// Whether mock keeps running
+enum Flow {
+    Continue,
+    Exit,
+}
+
+impl Mock {
+    fn send(&mut self, payload: Payload) -> io::Result<()> {
+        let json = serde_json::to_vec(&Package::new(payload))?;
+        write_frame(&mut self.stream, &json)
+    }
+
+    fn run(&mut self) -> io::Result<()> {
+        if let Some(name) = self.script.name.clone() {
+            let mut info = PluginInfo::new(&name, None);
+            info.fallback = self.script.fallback;
+            info.paging = self.script.paging;
+            info.preview = self.script.preview.is_some();
+            self.send(Payload::Register(info))?;
+        }
+
+        loop {
+            let package = match self.pending.pop_front() {
+                Some(package) => package,
+                None => match self.incoming.recv() {
+                    Ok(package) => package,
+                    // runner closed connection
+                    Err(_) => return Ok(()),
+                },
+            };
+
+            if let Flow::Exit = self.handle(package)? {
+                return Ok(());
+            }
+        }
+    }
+
+    fn handle(&mut self, package: Package) -> io::Result<Flow> {
+        let id = package.get_id();
+        match package.payload {
+            Payload::Command(Command::GetData(query)) => return self.answer(id, query),
+            Payload::Command(Command::Activate(hit_id)) => self.activate(id, hit_id)?,
+            Payload::Command(Command::GetPreview(_)) => {
+                thread::sleep(Duration::from_millis(self.script.preview_delay));
+                self.send(Payload::Preview((id, self.script.preview.clone())))?;
+            }
+            Payload::Command(Command::Configure(settings)) => {
+                self.send(Payload::Result((id, Ok(()))))?;
+                let settings = serde_json::to_string(&settings)?;
+                self.send(Payload::Status(Some(Status::new(
+                    Severity::Info,
+                    &settings,
+                ))))?;
+            }
+            Payload::Command(Command::Quit) => {
+                self.send(Payload::Result((id, Ok(()))))?;
+                return Ok(Flow::Exit);
+            }
+            // nothing is being sent, acknowledges of hits
+            Payload::Command(Command::Abort) | Payload::Result(_) => {}
+            payload => eprintln!("mock: unexpected payload {:?}", payload),
+        }
+        Ok(Flow::Continue)
+    }
+
+    fn answer(&mut self, id: PackageId, query: Query) -> io::Result<Flow> {
+        match self.script.on_query.get(&query.text).cloned() {
+            None => {}
+            Some(OnQuery::Error(message)) => {
+                self.send(Payload::Result((id, Err(message))))?;
+                return Ok(Flow::Continue);
+            }
+            Some(OnQuery::Disconnect) => return Ok(Flow::Exit),
+            Some(OnQuery::Malformed) => {
+                write_frame(&mut self.stream, b"{\"result\": [")?;
+                return Ok(Flow::Continue);
+            }
+        }
+
+        self.send(Payload::Result((id, Ok(()))))?;
+
+        let (offset, limit) = match self.script.paging {
+            true => (query.offset, query.limit.unwrap_or(usize::MAX)),
+            false => (0, usize::MAX),
+        };
+        let hits = self
+            .script
+            .hits
+            .iter()
+            .skip(offset)
+            .take(limit)
+            .cloned()
+            .collect::<Vec<_>>();
+        for mut hit in hits {
+            if self.interrupted()? {
+                break;
+            }
+            hit.title = hit.title.replace("{query}", &query.text);
+            self.send(Payload::Hit(hit))?;
+        }
+
+        // the same for finished and aborted query
+        self.send(Payload::Command(Command::Abort))?;
+        Ok(Flow::Continue)
+    }
+
+    /// Waits `hit_delay` watching for commands that stop current answer
+    fn interrupted(&mut self) -> io::Result<bool> {
+        let deadline = Instant::now() + Duration::from_millis(self.script.hit_delay);
+        loop {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            let package = match self.incoming.recv_timeout(timeout) {
+                Ok(package) => package,
+                Err(RecvTimeoutError::Timeout) => return Ok(false),
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(io::ErrorKind::ConnectionAborted.into())
+                }
+            };
+
+            match &package.payload {
+                Payload::Command(Command::Abort) => return Ok(true),
+                Payload::Command(Command::GetData(_) | Command::Quit) => {
+                    self.pending.push_back(package);
+                    return Ok(true);
+                }
+                Payload::Result(_) => {}
+                _ => self.pending.push_back(package),
+            }
+        }
+    }
+
+    fn activate(&mut self, id: PackageId, hit_id: HitId) -> io::Result<()> {
+        let payload = if !self.script.hits.iter().any(|hit| hit.id == hit_id) {
+            Payload::Result((id, Err(format!("Unknown hit {}", hit_id))))
+        } else {
+            match self.script.activate.clone() {
+                Some(action) => Payload::Action((id, action)),
+                None => Payload::Result((id, Ok(()))),
+            }
+        };
+        self.send(payload)
+    }
+}
+
+fn main() -> ExitCode {
+    let script = match env::var(SCRIPT_ENV) {
+        Ok(json) => match serde_json::from_str::<MockScript>(&json) {
+            Ok(script) => script,
+            Err(e) => {
+                eprintln!("mock: invalid script: {}", e);
+                return ExitCode::FAILURE;
+            }
+        },
+        Err(_) => MockScript::default(),
+    };
+
+    let stream = match UnixStream::connect(path::socket()) {
+        Ok(stream) => stream,
+        Err(e) => {
+            eprintln!("mock: failed to connect: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let (sender, incoming) = mpsc::channel();
+    let mut reader = stream.try_clone().expect("Failed to clone stream");
+    thread::spawn(move || {
+        while let Ok(package) = read_package(&mut reader) {
+            if sender.send(package).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut mock = Mock {
+        script,
+        stream,
+        incoming,
+        pending: VecDeque::new(),
+    };
+    match mock.run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("mock: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,349 @@
+//! Scriptable mock plugin and harness for integration tests of the plugin host.
+//!
+//! [`Harness::run`] starts [`PluginHost`] socket service inside temporary runtime dir
+//! and drives it on the default main context, no display is needed.
+//! Mocks are separate processes (`unirun-mock-plugin` binary of this crate),
+//! host tells plugins from itself by process ID
+
+use std::{
+    collections::HashMap,
+    env, fs,
+    future::Future,
+    panic::{self, AssertUnwindSafe},
+    path::PathBuf,
+    process::{Child, Command, ExitStatus},
+    sync::{mpsc, Mutex, OnceLock, PoisonError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use gio::prelude::*;
+use serde::{Deserialize, Serialize};
+use unirun_core::{config::Config, Event, PluginHost};
+use unirun_if::{
+    package::{Action, Hit, Preview},
+    path,
+};
+
+/// Environment variable with JSON of [`MockScript`] for mock plugin
+pub const SCRIPT_ENV: &str = "UNIRUN_MOCK_SCRIPT";
+
+/// Overrides path of mock plugin binary
+pub const MOCK_PLUGIN_ENV: &str = "UNIRUN_MOCK_PLUGIN";
+
+/// How mock plugin misbehaves on `GetData`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum OnQuery {
+    /// `Err` with the message
+    Error(String),
+    /// Closes connection without answering
+    Disconnect,
+    /// Sends frame that is not JSON
+    Malformed,
+}
+
+/// Behavior of mock plugin
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct MockScript {
+    /// Sent with `Register` right after connecting. Without it plugin is named after its process
+    pub name: Option<String>,
+    pub fallback: bool,
+    /// Registers with `paging` and answers by pages. Otherwise `offset` and `limit` are ignored,
+    /// like older plugins do
+    pub paging: bool,
+    /// Answer of every `GetData`. `{query}` in titles is replaced with query text
+    pub hits: Vec<Hit>,
+    /// Pause before each hit, ms. `Abort` and new `GetData` are handled during it
+    pub hit_delay: u64,
+    /// Behavior on queries with the key text, others are answered with `hits`
+    pub on_query: HashMap<String, OnQuery>,
+    /// Answer of `Activate` for known hits. `None` answers plain `Ok`
+    pub activate: Option<Action>,
+    /// Registers with `preview` and answers every `GetPreview` with it
+    pub preview: Option<Preview>,
+    /// Pause before answering `GetPreview`, ms
+    pub preview_delay: u64,
+}
+
+impl MockScript {
+    pub fn named(name: &str) -> Self {
+        Self {
+            name: Some(name.to_owned()),
+            ..Default::default()
+        }
+    }
+
+    /// Hits with `titles`, use `{query}` to echo query text
+    pub fn with_hits(mut self, titles: &[&str]) -> Self {
+        self.hits = titles
+            .iter()
+            .map(|title| Hit::new(title, None, None, false))
+            .collect();
+        self
+    }
+
+    pub fn with_paging(mut self) -> Self {
+        self.paging = true;
+        self
+    }
+
+    pub fn with_hit_delay(mut self, delay: Duration) -> Self {
+        self.hit_delay = delay.as_millis() as u64;
+        self
+    }
+
+    pub fn on_query(mut self, text: &str, on_query: OnQuery) -> Self {
+        self.on_query.insert(text.to_owned(), on_query);
+        self
+    }
+
+    pub fn on_activate(mut self, action: Action) -> Self {
+        self.activate = Some(action);
+        self
+    }
+
+    pub fn with_preview(mut self, preview: Preview, delay: Duration) -> Self {
+        self.preview = Some(preview);
+        self.preview_delay = delay.as_millis() as u64;
+        self
+    }
+}
+
+/// Running mock plugin process. Killed on drop if still alive
+pub struct MockPlugin {
+    child: Child,
+}
+
+impl MockPlugin {
+    pub fn pid(&self) -> u64 {
+        self.child.id() as u64
+    }
+
+    /// Waits for process to exit by itself. `None` on timeout
+    pub async fn wait_exit(&mut self, timeout: Duration) -> Option<ExitStatus> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Ok(Some(status)) = self.child.try_wait() {
+                return Some(status);
+            }
+            if Instant::now() >= deadline {
+                return None;
+            }
+            glib::timeout_future(POLL_INTERVAL).await;
+        }
+    }
+}
+
+impl Drop for MockPlugin {
+    fn drop(&mut self) {
+        if let Ok(None) = self.child.try_wait() {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+}
+
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Default timeout of harness waits. Generous, as tests may run on loaded machine
+pub const TIMEOUT: Duration = Duration::from_secs(5);
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Host lives on the default main context. Futures left by one test are polled by the next one,
+/// so all tests run one by one on the same thread
+fn worker() -> &'static Mutex<mpsc::Sender<Job>> {
+    static WORKER: OnceLock<Mutex<mpsc::Sender<Job>>> = OnceLock::new();
+    WORKER.get_or_init(|| {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        thread::spawn(move || {
+            runtime_dir();
+            for job in receiver {
+                job();
+            }
+        });
+        Mutex::new(sender)
+    })
+}
+
+/// Temporary `XDG_RUNTIME_DIR` shared by the whole test process.
+/// GLib reads it once, so it must be set before anything asks for runtime dir
+fn runtime_dir() -> &'static PathBuf {
+    static DIR: OnceLock<PathBuf> = OnceLock::new();
+    DIR.get_or_init(|| {
+        let dir = tempfile::Builder::new()
+            .prefix("unirun-test-")
+            .tempdir()
+            .expect("Failed to create runtime dir")
+            .keep();
+        // mocks inherit it and connect to the same socket
+        env::set_var("XDG_RUNTIME_DIR", &dir);
+        dir
+    })
+}
+
+/// Mock plugin binary built alongside tests, `target/<profile>/unirun-mock-plugin`
+pub fn mock_plugin_binary() -> PathBuf {
+    if let Some(binary) = env::var_os(MOCK_PLUGIN_ENV) {
+        return binary.into();
+    }
+
+    // test binaries live in `target/<profile>/deps`
+    let exe = env::current_exe().expect("Failed to get current exe");
+    exe.ancestors()
+        .skip(1)
+        .take(2)
+        .map(|dir| dir.join("unirun-mock-plugin"))
+        .find(|binary| binary.exists())
+        .expect("unirun-mock-plugin is not built")
+}
+
+/// Polls `condition` until it holds. Returns `false` on timeout
+pub async fn wait_until(timeout: Duration, mut condition: impl FnMut() -> bool) -> bool {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if condition() {
+            return true;
+        }
+        if Instant::now() >= deadline {
+            return false;
+        }
+        glib::timeout_future(POLL_INTERVAL).await;
+    }
+}
+
+/// Plugin host listening on socket in temporary runtime dir
+pub struct Harness {
+    pub host: PluginHost,
+    events: async_channel::Receiver<Event>,
+    socket_service: gio::SocketService,
+}
+
+impl Harness {
+    /// Runs `test` with fresh host on the default main context of harness thread.
+    /// Panic of `test` is passed to the caller
+    pub fn run<F, Fut>(test: F)
+    where
+        F: FnOnce(Harness) -> Fut + Send + 'static,
+        Fut: Future<Output = ()>,
+    {
+        let (sender, receiver) = mpsc::channel();
+        let job = Box::new(move || {
+            // failed test must not break the following ones
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                glib::MainContext::default().block_on(async move {
+                    let harness = Harness::new(Config::default());
+                    test(harness).await;
+                })
+            }));
+            let _ = sender.send(result);
+        });
+
+        worker()
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .send(job)
+            .expect("Harness thread is gone");
+        if let Err(panic) = receiver.recv().expect("Harness thread is gone") {
+            panic::resume_unwind(panic);
+        }
+    }
+
+    fn new(config: Config) -> Self {
+        let socket = path::socket();
+        // left by a panicked test
+        let _ = fs::remove_file(&socket);
+
+        let host = PluginHost::new(config);
+        let socket_service = host
+            .listen(&socket)
+            .expect("Failed to listen plugin socket");
+        socket_service.start();
+
+        Self {
+            events: host.events(),
+            host,
+            socket_service,
+        }
+    }
+
+    /// Launches mock plugin and waits until host has it connected (and registered if script names it)
+    pub async fn spawn(&self, script: MockScript) -> MockPlugin {
+        let child = Command::new(mock_plugin_binary())
+            .env(
+                SCRIPT_ENV,
+                serde_json::to_string(&script).expect("Failed to serialize script"),
+            )
+            .spawn()
+            .expect("Failed to launch mock plugin");
+        let mock = MockPlugin { child };
+
+        let pid = mock.pid();
+        let ready = wait_until(TIMEOUT, || match self.host.plugin(pid) {
+            Some(plugin) => script
+                .name
+                .as_ref()
+                .is_none_or(|name| &plugin.name() == name),
+            None => false,
+        })
+        .await;
+        assert!(ready, "Mock plugin {} didn't connect", pid);
+
+        mock
+    }
+
+    /// Waits until host is not busy with query
+    pub async fn settle(&self) {
+        // query may be sent with a delay, so it must not be idle for a while
+        let mut idle_since = None;
+        let settled = wait_until(TIMEOUT, || {
+            if self.host.is_busy() {
+                idle_since = None;
+                return false;
+            }
+            idle_since.get_or_insert_with(Instant::now).elapsed() >= Duration::from_millis(50)
+        })
+        .await;
+        assert!(settled, "Host is still busy");
+    }
+
+    /// Sends `text` and waits until plugins answered
+    pub async fn query(&self, text: &str) {
+        self.host.query(text);
+        self.settle().await;
+    }
+
+    /// Titles of current results in order
+    pub fn titles(&self) -> Vec<String> {
+        self.host
+            .results()
+            .into_iter()
+            .map(|shown| shown.hit.title)
+            .collect()
+    }
+
+    /// Skips events until one matches `filter`. `None` on timeout
+    pub async fn wait_event<T>(&self, mut filter: impl FnMut(Event) -> Option<T>) -> Option<T> {
+        let deadline = Instant::now() + TIMEOUT;
+        loop {
+            while let Ok(event) = self.events.try_recv() {
+                if let Some(value) = filter(event) {
+                    return Some(value);
+                }
+            }
+            if Instant::now() >= deadline {
+                return None;
+            }
+            glib::timeout_future(POLL_INTERVAL).await;
+        }
+    }
+}
+
+impl Drop for Harness {
+    fn drop(&mut self) {
+        self.socket_service.stop();
+        self.socket_service.close();
+        let _ = fs::remove_file(path::socket());
+    }
+}
@@ -0,0 +1,334 @@
+use std::time::Duration;
+
+use serde_json::{json, Value};
+use unirun_core::{config::Config, Event};
+use unirun_if::package::{Action, HitId, Preview};
+use unirun_test_support::{wait_until, Harness, MockPlugin, MockScript, OnQuery, TIMEOUT};
+
+#[test]
+fn query_fans_out_to_all_plugins() {
+    Harness::run(|harness| async move {
+        let _first = harness
+            .spawn(MockScript::named("first").with_hits(&["first {query}"]))
+            .await;
+        let _second = harness
+            .spawn(MockScript::named("second").with_hits(&["second {query}", "second 2"]))
+            .await;
+
+        harness.query("fire").await;
+
+        let mut titles = harness.titles();
+        titles.sort();
+        assert_eq!(titles, ["first fire", "second 2", "second fire"]);
+    });
+}
+
+#[test]
+fn new_plugin_gets_current_query() {
+    Harness::run(|harness| async move {
+        let _first = harness
+            .spawn(MockScript::named("first").with_hits(&["first {query}"]))
+            .await;
+        harness.query("fire").await;
+
+        let _second = harness
+            .spawn(MockScript::named("second").with_hits(&["second {query}"]))
+            .await;
+        harness.settle().await;
+
+        assert_eq!(harness.host.current_query(), "fire");
+        let mut titles = harness.titles();
+        titles.sort();
+        assert_eq!(titles, ["first fire", "second fire"]);
+    });
+}
+
+#[test]
+fn new_query_aborts_previous_one() {
+    Harness::run(|harness| async move {
+        let _mock = harness
+            .spawn(
+                MockScript::named("slow")
+                    .with_hits(&["{query} 1", "{query} 2", "{query} 3"])
+                    .with_hit_delay(Duration::from_millis(100)),
+            )
+            .await;
+
+        harness.host.query("old");
+        assert!(wait_until(TIMEOUT, || harness.titles() == ["old 1"]).await);
+
+        harness.query("new").await;
+
+        assert_eq!(harness.titles(), ["new 1", "new 2", "new 3"]);
+    });
+}
+
+#[test]
+fn only_plugins_with_paging_get_pages() {
+    Harness::run(|harness| async move {
+        harness
+            .host
+            .set_config(toml::from_str("limit = 2").unwrap());
+        // sends everything on every page, like plugins that don't know about them
+        let _old = harness
+            .spawn(MockScript::named("old").with_hits(&["old 1", "old 2", "old 3"]))
+            .await;
+        let _paged = harness
+            .spawn(
+                MockScript::named("paged")
+                    .with_paging()
+                    .with_hits(&["paged 1", "paged 2", "paged 3"]),
+            )
+            .await;
+
+        // empty one was answered on connect, before plugin registered with paging
+        harness.query("page").await;
+        let mut titles = harness.titles();
+        titles.sort();
+        assert_eq!(titles, ["old 1", "old 2", "old 3", "paged 1", "paged 2"]);
+
+        harness.host.load_more();
+        harness.settle().await;
+        let mut titles = harness.titles();
+        titles.sort();
+        assert_eq!(
+            titles,
+            ["old 1", "old 2", "old 3", "paged 1", "paged 2", "paged 3"]
+        );
+    });
+}
+
+#[test]
+fn query_error_is_reported() {
+    Harness::run(|harness| async move {
+        let mock = harness
+            .spawn(
+                MockScript::named("broken")
+                    .on_query("anything", OnQuery::Error("no luck".to_owned())),
+            )
+            .await;
+
+        harness.query("anything").await;
+
+        let message = harness
+            .wait_event(|event| match event {
+                Event::Error { pid, message } if pid == mock.pid() => Some(message),
+                _ => None,
+            })
+            .await;
+        assert_eq!(message.as_deref(), Some("no luck"));
+        assert!(harness.titles().is_empty());
+    });
+}
+
+#[test]
+fn broken_plugins_are_dropped() {
+    Harness::run(|harness| async move {
+        let good = harness
+            .spawn(MockScript::named("good").with_hits(&["good"]))
+            .await;
+        let gone = harness
+            .spawn(MockScript::named("gone").on_query("query", OnQuery::Disconnect))
+            .await;
+        let garbage = harness
+            .spawn(MockScript::named("garbage").on_query("query", OnQuery::Malformed))
+            .await;
+
+        harness.host.query("query");
+
+        assert!(
+            wait_until(TIMEOUT, || harness.host.plugin(gone.pid()).is_none()
+                && harness.host.plugin(garbage.pid()).is_none())
+            .await
+        );
+        harness.settle().await;
+        assert!(harness.host.plugin(good.pid()).is_some());
+        assert_eq!(harness.titles(), ["good"]);
+    });
+}
+
+#[test]
+fn activation_goes_to_plugin_of_hit() {
+    Harness::run(|harness| async move {
+        let _first = harness
+            .spawn(
+                MockScript::named("first")
+                    .with_hits(&["first"])
+                    .on_activate(Action::Message("first".to_owned())),
+            )
+            .await;
+        let second = harness
+            .spawn(
+                MockScript::named("second")
+                    .with_hits(&["second"])
+                    .on_activate(Action::Message("second".to_owned())),
+            )
+            .await;
+
+        harness.query("").await;
+
+        let shown = harness
+            .host
+            .results()
+            .into_iter()
+            .find(|shown| shown.pid == second.pid())
+            .expect("No hit of second plugin");
+        let action = harness.host.activate(shown.pid, shown.hit.id).await;
+        assert_eq!(action, Some(Action::Message("second".to_owned())));
+
+        // plugin doesn't know hits of the other one
+        let action = harness.host.activate(second.pid(), HitId::new()).await;
+        assert_eq!(action, None);
+    });
+}
+
+#[test]
+fn preview_is_asked_only_from_plugins_supporting_it() {
+    Harness::run(|harness| async move {
+        let preview = Preview::Text {
+            text: "preview".to_owned(),
+            use_pango: false,
+        };
+        let plain = harness
+            .spawn(MockScript::named("plain").with_hits(&["plain"]))
+            .await;
+        let fast = harness
+            .spawn(
+                MockScript::named("fast")
+                    .with_hits(&["fast"])
+                    .with_preview(preview.clone(), Duration::ZERO),
+            )
+            .await;
+        let slow = harness
+            .spawn(
+                MockScript::named("slow")
+                    .with_hits(&["slow"])
+                    .with_preview(preview.clone(), Duration::from_secs(3)),
+            )
+            .await;
+
+        harness.query("").await;
+
+        let hit_of = |mock: &MockPlugin| {
+            harness
+                .host
+                .results()
+                .into_iter()
+                .find(|shown| shown.pid == mock.pid())
+                .map(|shown| shown.hit.id)
+                .expect("No hit of plugin")
+        };
+        assert_eq!(
+            harness.host.preview(plain.pid(), hit_of(&plain)).await,
+            None
+        );
+        assert_eq!(
+            harness.host.preview(fast.pid(), hit_of(&fast)).await,
+            Some(preview)
+        );
+        // gives up on plugin that takes too long
+        assert_eq!(harness.host.preview(slow.pid(), hit_of(&slow)).await, None);
+    });
+}
+
+#[test]
+fn plugins_quit_on_shutdown() {
+    Harness::run(|harness| async move {
+        let mut first = harness.spawn(MockScript::named("first")).await;
+        let mut second = harness.spawn(MockScript::named("second")).await;
+
+        harness.host.quit_plugins();
+
+        for mock in [&mut first, &mut second] {
+            let status = mock.wait_exit(TIMEOUT).await;
+            assert!(status.is_some_and(|status| status.success()));
+        }
+        assert!(wait_until(TIMEOUT, || harness.host.plugins().is_empty()).await);
+
+        let disconnected = harness
+            .wait_event(|event| match event {
+                Event::Disconnected(pid) if pid == second.pid() => Some(pid),
+                _ => None,
+            })
+            .await;
+        assert!(disconnected.is_some());
+    });
+}
+
+#[test]
+fn metrics_are_kept_across_restarts() {
+    Harness::run(|harness| async move {
+        let script = MockScript::named("measured")
+            .with_hits(&["{query} 1", "{query} 2"])
+            .on_query("bad", OnQuery::Error("no luck".to_owned()));
+        let mut mock = harness.spawn(script.clone()).await;
+        // query sent on connect is counted only if it isn't aborted by the next one
+        harness.settle().await;
+
+        harness.query("fire").await;
+        harness.query("bad").await;
+        assert!(harness.titles().is_empty());
+        harness.query("fire").await;
+        let hit = harness.host.results().remove(0);
+        harness.host.activate(hit.pid, hit.hit.id).await;
+
+        harness.host.quit_plugins();
+        assert!(mock.wait_exit(TIMEOUT).await.is_some());
+        assert!(wait_until(TIMEOUT, || harness.host.plugins().is_empty()).await);
+        let _restarted = harness.spawn(script).await;
+        harness.settle().await;
+
+        let stats = harness.host.stats();
+        assert_eq!(stats.len(), 1);
+        let stats = &stats[0];
+        assert_eq!(stats.name, "measured");
+        assert_eq!(stats.restarts, 1);
+        assert_eq!(stats.errors, 1);
+        assert_eq!(stats.activation.count, 1);
+        // empty query on the first connect and "fire" on restart,
+        // "fire" is answered from cache the second time
+        assert_eq!(stats.queries, 4);
+        assert_eq!(stats.first_hit.count, 3);
+        assert_eq!(stats.completion.count, 4);
+        assert_eq!(stats.hits_per_query, 1.5);
+    });
+}
+
+/// Mock echoes settings it got as its status
+async fn echoed_settings(harness: &Harness, mock: &MockPlugin) -> Option<Value> {
+    harness
+        .wait_event(|event| match event {
+            Event::Status {
+                pid,
+                status: Some(status),
+            } if pid == mock.pid() => serde_json::from_str(&status.message).ok(),
+            _ => None,
+        })
+        .await
+}
+
+#[test]
+fn plugin_settings_are_sent_on_register_and_reload() {
+    fn config(engine: &str) -> Config {
+        toml::from_str(&format!(
+            "[plugins.configured]\npriority = 5\nengine = {:?}\nresults = [1, 2]",
+            engine
+        ))
+        .unwrap()
+    }
+
+    Harness::run(|harness| async move {
+        harness.host.set_config(config("duckduckgo"));
+        let mock = harness.spawn(MockScript::named("configured")).await;
+        assert_eq!(
+            echoed_settings(&harness, &mock).await,
+            Some(json!({ "engine": "duckduckgo", "results": [1, 2] }))
+        );
+
+        harness.host.set_config(config("searx"));
+        assert_eq!(
+            echoed_settings(&harness, &mock).await,
+            Some(json!({ "engine": "searx", "results": [1, 2] }))
+        );
+    });
+}
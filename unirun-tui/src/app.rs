@@ -0,0 +1,256 @@
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+};
+
+#[allow(unused_imports)]
+use log::*;
+use ratatui::{
+    crossterm::event::{Event as TermEvent, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    widgets::ListState,
+};
+use unirun_core::{Event, PluginHit, PluginHost};
+use unirun_if::package::{Action, ClipboardContent, Status};
+
+pub enum Message {
+    Host(Event),
+    Input(TermEvent),
+    /// Answer of plugin on activation
+    Action(u64, Action),
+}
+
+pub struct App {
+    pub host: PluginHost,
+    sender: async_channel::Sender<Message>,
+    pub entry: String,
+    /// Copy of host results, refreshed on every change
+    pub hits: Vec<PluginHit>,
+    pub list_state: ListState,
+    /// Rows of the list that fit the screen, for Page Down
+    pub page_height: usize,
+    /// Message of plugin or error shown in the status line
+    pub message: Option<String>,
+    pub statuses: BTreeMap<u64, (String, Status)>,
+    pub glyphs: bool,
+    /// Plugin metrics are drawn over the list
+    pub show_stats: bool,
+    quit: bool,
+}
+
+impl App {
+    pub fn new(host: PluginHost, sender: async_channel::Sender<Message>, glyphs: bool) -> Self {
+        Self {
+            host,
+            sender,
+            entry: String::new(),
+            hits: Vec::new(),
+            list_state: ListState::default(),
+            page_height: 0,
+            message: None,
+            statuses: BTreeMap::new(),
+            glyphs,
+            show_stats: false,
+            quit: false,
+        }
+    }
+
+    pub fn should_quit(&self) -> bool {
+        self.quit
+    }
+
+    pub fn handle_message(&mut self, message: Message) {
+        match message {
+            Message::Host(event) => self.handle_host_event(event),
+            Message::Input(TermEvent::Key(key)) if key.kind == KeyEventKind::Press => {
+                self.handle_key(key)
+            }
+            Message::Input(_) => {}
+            Message::Action(plugin_pid, action) => self.handle_action(action, plugin_pid),
+        }
+    }
+
+    fn handle_host_event(&mut self, event: Event) {
+        match event {
+            Event::Splice { position, .. } => {
+                self.hits = self.host.results();
+                // first row is selected by default like in window
+                if position == 0 || self.list_state.selected().is_none() {
+                    self.list_state.select((!self.hits.is_empty()).then_some(0));
+                } else if let Some(selected) = self.list_state.selected() {
+                    self.list_state
+                        .select(Some(selected.min(self.hits.len().saturating_sub(1))));
+                }
+            }
+            Event::Update { .. } => self.hits = self.host.results(),
+            Event::Status { pid, status } => match status {
+                Some(status) => {
+                    let name = self
+                        .host
+                        .plugin(pid)
+                        .map(|plugin| plugin.name())
+                        .unwrap_or_else(|| pid.to_string());
+                    self.statuses.insert(pid, (name, status));
+                }
+                None => {
+                    self.statuses.remove(&pid);
+                }
+            },
+            Event::Disconnected(pid) => {
+                self.statuses.remove(&pid);
+            }
+            Event::Error { pid, message } => {
+                let name = self
+                    .host
+                    .plugin(pid)
+                    .map(|plugin| plugin.name())
+                    .unwrap_or_else(|| pid.to_string());
+                self.message = Some(format!("{}: {}", name, message));
+            }
+            Event::Quit => self.quit = true,
+            Event::Connected(_) | Event::Registered(_) | Event::Progress => {}
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) {
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Char('c') | KeyCode::Char('d') if control => self.quit = true,
+            KeyCode::Char('u') if control => self.set_entry(String::new()),
+            KeyCode::Char('n') if control => self.select_next(),
+            KeyCode::Char('p') if control => self.select_previous(),
+            KeyCode::Char(c) if !control => {
+                let mut entry = self.entry.clone();
+                entry.push(c);
+                self.set_entry(entry);
+            }
+            KeyCode::Backspace if self.entry.is_empty() => {
+                self.pop_view();
+            }
+            KeyCode::Backspace => {
+                let mut entry = self.entry.clone();
+                entry.pop();
+                self.set_entry(entry);
+            }
+            KeyCode::Down => self.select_next(),
+            KeyCode::Up => self.select_previous(),
+            KeyCode::PageDown => self.page_down(),
+            KeyCode::PageUp => {
+                let selected = self.list_state.selected().unwrap_or_default();
+                self.select(selected.saturating_sub(self.page_height.max(1)));
+            }
+            KeyCode::Enter => self.activate(),
+            KeyCode::F(12) => self.show_stats = !self.show_stats,
+            KeyCode::Esc if !self.pop_view() => self.quit = true,
+            _ => {}
+        }
+    }
+
+    /// Entry changes are sent as query right away, host debounces plugins that need it
+    fn set_entry(&mut self, text: String) {
+        self.message = None;
+        self.entry = text;
+        self.host.query(&self.entry);
+    }
+
+    fn select(&mut self, index: usize) {
+        if !self.hits.is_empty() {
+            self.list_state.select(Some(index.min(self.hits.len() - 1)));
+        }
+    }
+
+    fn select_next(&mut self) {
+        match self.list_state.selected() {
+            Some(selected) => self.select(selected + 1),
+            None => self.select(0),
+        }
+    }
+
+    fn select_previous(&mut self) {
+        let selected = self.list_state.selected().unwrap_or_default();
+        self.select(selected.saturating_sub(1));
+    }
+
+    /// On the last hit asks plugins for the next page
+    fn page_down(&mut self) {
+        let selected = self.list_state.selected().unwrap_or_default();
+        if selected + 1 >= self.hits.len() {
+            self.host.load_more();
+        } else {
+            self.select(selected + self.page_height.max(1));
+        }
+    }
+
+    fn activate(&mut self) {
+        let Some(hit) = self
+            .list_state
+            .selected()
+            .and_then(|selected| self.hits.get(selected))
+        else {
+            return;
+        };
+
+        let host = self.host.clone();
+        let sender = self.sender.clone();
+        let (plugin_pid, id) = (hit.pid, hit.hit.id.clone());
+        glib::spawn_future_local(async move {
+            if let Some(action) = host.activate(plugin_pid, id).await {
+                let _ = sender.send(Message::Action(plugin_pid, action)).await;
+            }
+        });
+    }
+
+    /// Returns `false` if there is no view to go back from
+    fn pop_view(&mut self) -> bool {
+        if !self.host.pop_view() {
+            return false;
+        }
+
+        let query = self.host.current_query();
+        if self.entry != query {
+            self.set_entry(query);
+        }
+        true
+    }
+
+    fn handle_action(&mut self, action: Action, plugin_pid: u64) {
+        debug!("Handling activation action: {:?}", action);
+
+        match action {
+            Action::Close => self.quit = true,
+            Action::KeepOpen => {}
+            Action::SetQuery(query) => self.set_entry(query),
+            Action::Clipboard { content, close } => {
+                match content {
+                    ClipboardContent::Text(text) => self.copy(&text),
+                    content => {
+                        warn!(
+                            "Clipboard content is not supported in terminal: {:?}",
+                            content
+                        );
+                        self.message = Some("Can't copy it in terminal".to_owned());
+                    }
+                }
+                self.quit = close;
+            }
+            Action::Message(message) => self.message = Some(message),
+            Action::Refresh => self.host.refresh(plugin_pid),
+            Action::Push { context, title } => {
+                self.host.push_view(plugin_pid, context, title);
+                self.set_entry(String::new());
+            }
+        }
+    }
+
+    /// Through OSC 52, so it works over SSH in terminals that support it
+    fn copy(&mut self, text: &str) {
+        let sequence = format!("\x1b]52;c;{}\x07", glib::base64_encode(text.as_bytes()));
+        let mut stdout = io::stdout();
+        if let Err(e) = stdout
+            .write_all(sequence.as_bytes())
+            .and_then(|_| stdout.flush())
+        {
+            error!("Failed to copy: {}", e);
+        }
+        self.message = Some("Copied".to_owned());
+    }
+}
@@ -0,0 +1,180 @@
+mod app;
+mod markup;
+mod ui;
+
+use std::{
+    env,
+    fs::{self, File},
+    path::PathBuf,
+    process::{self, ExitCode, Stdio},
+    thread,
+};
+
+use app::{App, Message};
+use gio::prelude::*;
+#[allow(unused_imports)]
+use log::*;
+use ratatui::crossterm::event;
+use unirun_core::{config::Config, metrics, plugin_binaries, PluginHost};
+use unirun_if::{path, record};
+
+const USAGE: &str = "\
+Usage: unirun-tui [--glyphs] [--stats] [--record <file.jsonl>]
+
+Options:
+    --glyphs                show glyph in front of hit instead of its icon
+    --stats                 print plugin metrics on exit
+    --record <file.jsonl>   log all plugin packages to file, see unirun-replay
+    -h, --help              print this help
+
+Keys:
+    Up/Down, Ctrl+P/Ctrl+N  select hit
+    Enter                   activate hit
+    Page Down               next page of results on the last hit
+    Ctrl+U                  clear query
+    Escape                  go back from child view or quit
+    F12                     show or hide plugin metrics
+    Ctrl+C                  quit
+
+Logs are written to $XDG_RUNTIME_DIR/com.bzglve/unirun-tui.log if RUST_LOG is set";
+
+fn main() -> ExitCode {
+    let mut glyphs = false;
+    let mut print_stats = false;
+    let mut record_file = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--glyphs" => glyphs = true,
+            "--stats" => print_stats = true,
+            "--record" if record_file.is_none() => match args.next() {
+                Some(file) => record_file = Some(PathBuf::from(file)),
+                None => {
+                    eprintln!("{}", USAGE);
+                    return ExitCode::FAILURE;
+                }
+            },
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return ExitCode::SUCCESS;
+            }
+            _ => {
+                eprintln!("{}", USAGE);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    // terminal belongs to the UI
+    if env::var_os("RUST_LOG").is_some() {
+        match File::create(path::runtime().join("unirun-tui.log")) {
+            Ok(file) => env_logger::Builder::from_default_env()
+                .target(env_logger::Target::Pipe(Box::new(file)))
+                .init(),
+            Err(e) => eprintln!("Failed to create log file: {}", e),
+        }
+    }
+
+    if let Some(file) = &record_file {
+        if let Err(e) = record::start(file) {
+            eprintln!("Failed to record packages to {}: {}", file.display(), e);
+            return ExitCode::FAILURE;
+        }
+    }
+
+    let host = PluginHost::new(Config::load());
+    let socket_service = match host.listen(&path::socket()) {
+        Ok(socket_service) => socket_service,
+        Err(e) => {
+            eprintln!(
+                "Failed to listen plugin socket, is unirun already running? {}",
+                e
+            );
+            return ExitCode::FAILURE;
+        }
+    };
+    socket_service.start();
+    launch_plugins();
+
+    let (sender, receiver) = async_channel::unbounded();
+
+    let events = host.events();
+    let host_sender = sender.clone();
+    glib::spawn_future_local(async move {
+        while let Ok(event) = events.recv().await {
+            if host_sender.send(Message::Host(event)).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    // crossterm reads are blocking
+    let input_sender = sender.clone();
+    thread::spawn(move || loop {
+        match event::read() {
+            Ok(event) => {
+                if input_sender.send_blocking(Message::Input(event)).is_err() {
+                    break;
+                }
+            }
+            Err(e) => {
+                error!("Failed to read terminal event: {}", e);
+                break;
+            }
+        }
+    });
+
+    let main_loop = glib::MainLoop::new(None, false);
+    let mut terminal = ratatui::init();
+    let mut app = App::new(host.clone(), sender, glyphs);
+
+    glib::spawn_future_local(glib::clone!(
+        #[strong]
+        main_loop,
+        async move {
+            loop {
+                if let Err(e) = terminal.draw(|frame| ui::draw(frame, &mut app)) {
+                    error!("Failed to draw: {}", e);
+                    break;
+                }
+                let Ok(message) = receiver.recv().await else {
+                    break;
+                };
+                app.handle_message(message);
+                if app.should_quit() {
+                    break;
+                }
+            }
+            main_loop.quit();
+        }
+    ));
+
+    main_loop.run();
+    ratatui::restore();
+
+    if print_stats {
+        print!("{}", metrics::table(&host.stats()));
+    }
+
+    host.finalize();
+    let socket = path::socket();
+    if let Err(e) = fs::remove_file(&socket) {
+        error!("Failed to remove socket file {}: {}", socket.display(), e);
+    }
+
+    ExitCode::SUCCESS
+}
+
+/// Plugins write logs to stderr, that would break the screen
+fn launch_plugins() {
+    for binary in plugin_binaries() {
+        if let Err(e) = process::Command::new(&binary)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+        {
+            error!("Failed to launch {}: {}", binary.display(), e);
+        }
+    }
+}
@@ -0,0 +1,201 @@
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::Span,
+};
+use unirun_core::markup::{decode_entity, plain_text};
+
+/// Characters of Pango markup with their style. `None` if markup is broken
+fn parse(markup: &str, base: Style) -> Option<Vec<(char, Style)>> {
+    let mut chars = Vec::new();
+    let mut stack = vec![base];
+    let mut rest = markup;
+
+    while let Some(c) = rest.chars().next() {
+        let style = *stack.last()?;
+        match c {
+            '<' => {
+                let end = rest.find('>')?;
+                let tag = &rest[1..end];
+                rest = &rest[end + 1..];
+
+                if tag.starts_with('/') {
+                    // base stays
+                    if stack.len() > 1 {
+                        stack.pop();
+                    }
+                } else {
+                    stack.push(tag_style(tag, style));
+                }
+            }
+            '&' => {
+                let end = rest.find(';')?;
+                chars.push((decode_entity(&rest[1..end])?, style));
+                rest = &rest[end + 1..];
+            }
+            c => {
+                chars.push((c, style));
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+
+    Some(chars)
+}
+
+/// `style` with attributes of opening `tag` applied. Unknown ones change nothing
+fn tag_style(tag: &str, style: Style) -> Style {
+    let mut parts = tag.split_whitespace();
+    match parts.next().unwrap_or_default() {
+        "b" => style.add_modifier(Modifier::BOLD),
+        "i" => style.add_modifier(Modifier::ITALIC),
+        "u" => style.add_modifier(Modifier::UNDERLINED),
+        "s" => style.add_modifier(Modifier::CROSSED_OUT),
+        "span" => parts.fold(style, |style, attribute| {
+            let Some((key, value)) = attribute.split_once('=') else {
+                return style;
+            };
+            let value = value.trim_matches(['"', '\'']);
+            match (key, value) {
+                ("weight" | "font_weight", "bold" | "heavy" | "ultrabold") => {
+                    style.add_modifier(Modifier::BOLD)
+                }
+                ("style" | "font_style", "italic" | "oblique") => {
+                    style.add_modifier(Modifier::ITALIC)
+                }
+                ("underline", value) if value != "none" => style.add_modifier(Modifier::UNDERLINED),
+                ("strikethrough", "true") => style.add_modifier(Modifier::CROSSED_OUT),
+                ("foreground" | "fgcolor" | "color", value) => match value.parse::<Color>() {
+                    Ok(color) => style.fg(color),
+                    Err(_) => style,
+                },
+                ("background" | "bgcolor", value) => match value.parse::<Color>() {
+                    Ok(color) => style.bg(color),
+                    Err(_) => style,
+                },
+                _ => style,
+            }
+        }),
+        _ => style,
+    }
+}
+
+/// Spans of hit text. Pango markup is converted to terminal styles,
+/// chars in `highlight` ranges are emphasized
+pub fn spans(
+    text: &str,
+    use_pango: bool,
+    highlight: &[(usize, usize)],
+    base: Style,
+) -> Vec<Span<'static>> {
+    let mut chars = if use_pango {
+        parse(text, base).unwrap_or_else(|| {
+            plain_text(text.to_owned(), true)
+                .chars()
+                .map(|c| (c, base))
+                .collect()
+        })
+    } else {
+        text.chars().map(|c| (c, base)).collect()
+    };
+
+    for &(start, end) in highlight {
+        for (_, style) in chars.iter_mut().take(end).skip(start) {
+            *style = style.fg(Color::Yellow).add_modifier(Modifier::BOLD);
+        }
+    }
+
+    let mut spans: Vec<Span> = Vec::new();
+    for (c, style) in chars {
+        match spans.last_mut() {
+            Some(span) if span.style == style => span.content.to_mut().push(c),
+            _ => spans.push(Span::styled(c.to_string(), style)),
+        }
+    }
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bold() -> Style {
+        Style::default().add_modifier(Modifier::BOLD)
+    }
+
+    fn highlighted(style: Style) -> Style {
+        style.fg(Color::Yellow).add_modifier(Modifier::BOLD)
+    }
+
+    fn text(spans: &[Span]) -> Vec<(String, Style)> {
+        spans
+            .iter()
+            .map(|span| (span.content.to_string(), span.style))
+            .collect()
+    }
+
+    #[test]
+    fn nested_tags() {
+        let chars = parse("<b>a<i>b</i></b>c", Style::default()).unwrap();
+        assert_eq!(
+            chars,
+            [
+                ('a', bold()),
+                ('b', bold().add_modifier(Modifier::ITALIC)),
+                ('c', Style::default()),
+            ]
+        );
+
+        let chars = parse(
+            "<span foreground=\"red\" weight='bold'>a</span>",
+            Style::default(),
+        );
+        assert_eq!(chars.unwrap(), [('a', bold().fg(Color::Red))]);
+
+        // extra closing tag doesn't drop base style
+        let base = Style::default().fg(Color::Gray);
+        assert_eq!(parse("</b>a", base).unwrap(), [('a', base)]);
+    }
+
+    #[test]
+    fn broken_markup_falls_back() {
+        assert_eq!(parse("<b>a", Style::default()).map(|c| c.len()), Some(1));
+        assert_eq!(parse("a <b", Style::default()), None);
+        assert_eq!(parse("a & b", Style::default()), None);
+
+        let styled = spans("a &unknown; b", true, &[], Style::default());
+        assert_eq!(
+            text(&styled),
+            [("a &unknown; b".to_owned(), Style::default())]
+        );
+    }
+
+    #[test]
+    fn numeric_entities() {
+        let chars = parse("&#65;&#x42;", Style::default()).unwrap();
+        assert_eq!(chars.into_iter().map(|(c, _)| c).collect::<String>(), "AB");
+    }
+
+    #[test]
+    fn highlight_is_applied_to_decoded_text() {
+        // ranges are chars of plain text, `&amp;` is one of them
+        let styled = spans("<b>a</b> &amp; b", true, &[(2, 3)], Style::default());
+        assert_eq!(
+            text(&styled),
+            [
+                ("a".to_owned(), bold()),
+                (" ".to_owned(), Style::default()),
+                ("&".to_owned(), highlighted(Style::default())),
+                (" b".to_owned(), Style::default()),
+            ]
+        );
+
+        let styled = spans("a &amp; b", false, &[(0, 1)], Style::default());
+        assert_eq!(
+            text(&styled),
+            [
+                ("a".to_owned(), highlighted(Style::default())),
+                (" &amp; b".to_owned(), Style::default()),
+            ]
+        );
+    }
+}
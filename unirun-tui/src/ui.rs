@@ -0,0 +1,157 @@
+use ratatui::{
+    layout::{Constraint, Layout, Position},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Clear, List, ListItem, Paragraph},
+    Frame,
+};
+use unirun_core::{metrics, PluginHit};
+use unirun_if::package::{Icon, Severity};
+
+use crate::{app::App, markup};
+
+const DESCRIPTION_STYLE: Style = Style::new().fg(Color::DarkGray);
+
+/// Glyph of icon theme name, by the most common words in it
+fn glyph(icon: Option<&Icon>) -> &'static str {
+    const GLYPHS: &[(&str, &str)] = &[
+        ("folder", "📁"),
+        ("directory", "📁"),
+        ("terminal", "⌨"),
+        ("web", "🌐"),
+        ("browser", "🌐"),
+        ("firefox", "🌐"),
+        ("chrom", "🌐"),
+        ("mail", "✉"),
+        ("music", "♫"),
+        ("audio", "♫"),
+        ("video", "▶"),
+        ("image", "🖼"),
+        ("picture", "🖼"),
+        ("text", "📄"),
+        ("document", "📄"),
+        ("calc", "🧮"),
+        ("settings", "⚙"),
+        ("preferences", "⚙"),
+        ("system", "⚙"),
+        ("search", "🔍"),
+    ];
+
+    let Some(Icon::Name(name)) = icon else {
+        return "•";
+    };
+    let name = name.to_lowercase();
+    GLYPHS
+        .iter()
+        .find(|(word, _)| name.contains(word))
+        .map_or("•", |(_, glyph)| glyph)
+}
+
+fn hit_item(hit: &PluginHit, glyphs: bool) -> ListItem<'static> {
+    let hit = &hit.hit;
+    let highlight = hit.highlight.clone().unwrap_or_default();
+
+    let mut spans = Vec::new();
+    if glyphs {
+        spans.push(Span::raw(format!("{} ", glyph(hit.icon.as_ref()))));
+    }
+    spans.extend(markup::spans(
+        &hit.title,
+        hit.use_pango,
+        &highlight.title,
+        Style::new(),
+    ));
+    if let Some(description) = &hit.description {
+        spans.push(Span::raw("  "));
+        spans.extend(markup::spans(
+            description,
+            hit.use_pango,
+            &highlight.description,
+            DESCRIPTION_STYLE,
+        ));
+    }
+    ListItem::new(Line::from(spans))
+}
+
+/// Message, plugin statuses or search state
+fn status_line(app: &App) -> Line<'static> {
+    if let Some(message) = &app.message {
+        return Line::raw(message.clone());
+    }
+
+    let statuses = app
+        .statuses
+        .values()
+        .map(|(name, status)| {
+            let mut text = format!("{}: {}", name, status.message);
+            if let Some(progress) = status.progress {
+                text.push_str(&format!(" {}/{}", progress.done, progress.total));
+            }
+            let style = match status.severity {
+                Severity::Info => Style::new(),
+                Severity::Warning => Style::new().fg(Color::Yellow),
+                Severity::Error => Style::new().fg(Color::Red),
+            };
+            Span::styled(text, style)
+        })
+        .collect::<Vec<_>>();
+    if !statuses.is_empty() {
+        return Line::from(
+            statuses
+                .into_iter()
+                .flat_map(|span| [span, Span::raw("  ")])
+                .collect::<Vec<_>>(),
+        );
+    }
+
+    if app.host.is_busy() {
+        Line::raw("Searching…")
+    } else if app.hits.is_empty() {
+        Line::raw("No results")
+    } else {
+        Line::raw(format!("{} results", app.hits.len()))
+    }
+    .style(DESCRIPTION_STYLE)
+}
+
+pub fn draw(frame: &mut Frame, app: &mut App) {
+    let [entry_area, list_area, status_area] = Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Min(1),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+
+    let titles = app.host.view_titles();
+    let title = if titles.is_empty() {
+        " unirun ".to_owned()
+    } else {
+        format!(" {} ", titles.join(" › "))
+    };
+    let entry = Paragraph::new(app.entry.as_str()).block(Block::bordered().title(title));
+    frame.render_widget(entry, entry_area);
+    frame.set_cursor_position(Position::new(
+        // wide chars take two cells
+        entry_area.x + 1 + Span::raw(app.entry.as_str()).width() as u16,
+        entry_area.y + 1,
+    ));
+
+    app.page_height = list_area.height as usize;
+    let list = List::new(app.hits.iter().map(|hit| hit_item(hit, app.glyphs)))
+        .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+    frame.render_stateful_widget(list, list_area, &mut app.list_state);
+
+    if app.show_stats {
+        let table = metrics::table(&app.host.stats());
+        let height = (table.lines().count() as u16 + 2).min(list_area.height);
+        let [_, stats_area] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(height)]).areas(list_area);
+        frame.render_widget(Clear, stats_area);
+        frame.render_widget(
+            Paragraph::new(table).block(Block::bordered().title(" stats ")),
+            stats_area,
+        );
+    }
+
+    frame.render_widget(Paragraph::new(status_line(app)), status_area);
+}
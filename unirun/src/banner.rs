@@ -0,0 +1,48 @@
+use gtk::{glib, prelude::*};
+
+/// Dismissible plugin error shown above results
+pub fn build_error_banner() -> gtk::Revealer {
+    let label = gtk::Label::builder()
+        .xalign(0.0)
+        .hexpand(true)
+        .wrap(true)
+        .wrap_mode(gtk::pango::WrapMode::WordChar)
+        .selectable(true)
+        .build();
+    let close = gtk::Button::builder()
+        .icon_name("window-close-symbolic")
+        .valign(gtk::Align::Center)
+        .css_classes(["flat"])
+        .tooltip_text("Dismiss")
+        .build();
+
+    let hbox = gtk::Box::new(gtk::Orientation::Horizontal, 8);
+    hbox.add_css_class("error");
+    hbox.append(&gtk::Image::from_icon_name("dialog-error-symbolic"));
+    hbox.append(&label);
+    hbox.append(&close);
+
+    let banner = gtk::Revealer::builder()
+        .transition_type(gtk::RevealerTransitionType::SlideDown)
+        .child(&hbox)
+        .build();
+    close.connect_clicked(glib::clone!(
+        #[weak]
+        banner,
+        move |_| banner.set_reveal_child(false)
+    ));
+
+    banner
+}
+
+/// Newer error replaces shown one
+pub fn show_error(banner: &gtk::Revealer, plugin_name: &str, error: &str) {
+    let label = banner
+        .child()
+        .and_then(|hbox| hbox.first_child())
+        .and_then(|image| image.next_sibling())
+        .and_downcast::<gtk::Label>()
+        .expect("Error banner has no label");
+    label.set_text(&format!("{}: {}", plugin_name, error));
+    banner.set_reveal_child(true);
+}
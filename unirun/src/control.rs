@@ -0,0 +1,163 @@
+use std::{cell::RefCell, fs, rc::Rc};
+
+use gtk::{
+    gio,
+    glib::{self, clone},
+    prelude::*,
+};
+#[allow(unused_imports)]
+use log::*;
+use unirun_if::{
+    control::{PluginEntry, Request, Response, RunnerStatus},
+    package::{Command, Package, Payload},
+    path,
+};
+
+use unirun_core::{config::Config, launch_plugin, launch_plugins};
+
+use crate::{
+    types::RuntimeData,
+    utils::{hide_window, show_window},
+};
+
+/// Socket service answering `unirun-ctl`. One request per connection
+pub fn build_control_service(
+    runtime_data: Rc<RefCell<RuntimeData>>,
+) -> Result<gio::SocketService, glib::Error> {
+    let socket_path = path::control_socket();
+    // left by crashed instance, main socket would fail first if unirun is still running
+    if socket_path.exists() {
+        if let Err(e) = fs::remove_file(&socket_path) {
+            error!("Failed to remove stale control socket: {}", e);
+        }
+    }
+
+    let control_service = gio::SocketService::new();
+    control_service.add_address(
+        &gio::UnixSocketAddress::new(&socket_path),
+        gio::SocketType::Stream,
+        gio::SocketProtocol::Default,
+        glib::Object::NONE,
+    )?;
+
+    control_service.connect_incoming(move |_, connection, _| {
+        glib::spawn_future_local(clone!(
+            #[strong]
+            connection,
+            #[strong]
+            runtime_data,
+            async move {
+                if let Err(e) = serve(&connection, runtime_data).await {
+                    warn!("Control connection failed: {}", e);
+                }
+            }
+        ));
+        true
+    });
+
+    Ok(control_service)
+}
+
+async fn serve(
+    connection: &gio::SocketConnection,
+    runtime_data: Rc<RefCell<RuntimeData>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let input = gio::DataInputStream::new(&connection.input_stream());
+    let Some(line) = input.read_line_utf8_future(glib::Priority::DEFAULT).await? else {
+        return Ok(());
+    };
+
+    let response = match serde_json::from_str::<Request>(&line) {
+        Ok(request) => {
+            debug!("Control request: {:?}", request);
+            handle_request(request, runtime_data)
+        }
+        Err(e) => Response::Error(format!("Bad request: {}", e)),
+    };
+
+    let mut json = serde_json::to_string(&response)?;
+    json.push('\n');
+    connection
+        .output_stream()
+        .write_all_future(json.into_bytes(), glib::Priority::DEFAULT)
+        .await
+        .map_err(|(_, e)| e)?;
+    Ok(())
+}
+
+pub fn handle_request(request: Request, runtime_data: Rc<RefCell<RuntimeData>>) -> Response {
+    let widgets = runtime_data.borrow().widgets.clone();
+
+    match request {
+        Request::Show => show_window(runtime_data),
+        Request::Hide => hide_window(runtime_data),
+        Request::Toggle => match widgets {
+            Some(widgets) if widgets.window.is_visible() => hide_window(runtime_data),
+            _ => show_window(runtime_data),
+        },
+        Request::Query(text) => {
+            show_window(runtime_data);
+            if let Some(widgets) = widgets {
+                widgets.entry.set_text(&text);
+                widgets.entry.set_position(-1);
+            }
+        }
+        Request::ReloadConfig => {
+            runtime_data.borrow().host.set_config(Config::load());
+            info!("Config reloaded");
+        }
+        Request::ReloadPlugins => {
+            // plugins leave on quit and get removed by their reader loops
+            runtime_data.borrow().host.quit_plugins();
+            launch_plugins();
+        }
+        Request::ListPlugins => {
+            return Response::Plugins(
+                runtime_data
+                    .borrow()
+                    .host
+                    .plugins()
+                    .iter()
+                    .map(|plugin| PluginEntry {
+                        pid: plugin.pid(),
+                        name: plugin.name(),
+                        fallback: plugin.is_fallback(),
+                        busy: plugin.is_busy(),
+                        status: plugin.status(),
+                    })
+                    .collect(),
+            )
+        }
+        Request::RestartPlugin(name) => {
+            let Some(plugin) = runtime_data
+                .borrow()
+                .host
+                .plugins()
+                .into_iter()
+                .find(|plugin| plugin.name() == name)
+            else {
+                return Response::Error(format!("No plugin named {:?}", name));
+            };
+
+            let binary = match fs::read_link(format!("/proc/{}/exe", plugin.pid())) {
+                Ok(binary) => binary,
+                Err(e) => return Response::Error(format!("Failed to find executable: {}", e)),
+            };
+            plugin.send(Package::new(Payload::Command(Command::Quit)));
+            launch_plugin(&binary);
+        }
+        Request::Status => {
+            let runtime_data = runtime_data.borrow();
+            return Response::Status(RunnerStatus {
+                visible: widgets.is_some_and(|widgets| widgets.window.is_visible()),
+                query: runtime_data.host.current_query(),
+                hits: runtime_data.hit_store.n_items(),
+                plugins: runtime_data.host.plugins().len(),
+                views: runtime_data.host.view_titles(),
+            });
+        }
+        Request::Stats => return Response::Stats(runtime_data.borrow().host.stats()),
+    }
+
+    Response::Ok
+}
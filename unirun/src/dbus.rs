@@ -0,0 +1,201 @@
+use std::{cell::RefCell, rc::Rc, time::Duration};
+
+use gtk::{gio, glib, prelude::*};
+#[allow(unused_imports)]
+use log::*;
+use unirun_if::{
+    constants::MAIN_APP_ID,
+    control::{Request, Response},
+};
+
+use unirun_core::{markup::plain_text, PluginHit};
+
+use crate::{control::handle_request, types::RuntimeData};
+
+pub const OBJECT_PATH: &str = "/com/bzglve/unirun";
+
+const INTERFACE_XML: &str = r#"
+<node>
+  <interface name="com.bzglve.unirun">
+    <method name="Show">
+      <arg type="s" name="query" direction="in"/>
+    </method>
+    <method name="Hide"/>
+    <method name="Toggle"/>
+    <method name="Query">
+      <arg type="s" name="text" direction="in"/>
+      <arg type="a(ssss)" name="hits" direction="out"/>
+    </method>
+    <method name="ListPlugins">
+      <arg type="a(tsbb)" name="plugins" direction="out"/>
+    </method>
+    <signal name="Activated">
+      <arg type="s" name="plugin"/>
+      <arg type="s" name="hit"/>
+    </signal>
+    <signal name="VisibilityChanged">
+      <arg type="b" name="visible"/>
+    </signal>
+  </interface>
+</node>
+"#;
+
+/// How long `Query` waits for plugins to finish before answering with what it has
+const QUERY_TIMEOUT: Duration = Duration::from_secs(3);
+const QUERY_POLL: Duration = Duration::from_millis(50);
+
+const ERROR_FAILED: &str = "org.freedesktop.DBus.Error.Failed";
+
+/// Exports runner interface on the session bus connection of application.
+/// Bus name is the application id, so it has to be called after application registered
+pub fn register(
+    application: &gtk::Application,
+    runtime_data: Rc<RefCell<RuntimeData>>,
+) -> Result<(), glib::Error> {
+    let Some(connection) = application.dbus_connection() else {
+        warn!("Application is not on the session bus, D-Bus interface is not exported");
+        return Ok(());
+    };
+
+    let node_info = gio::DBusNodeInfo::for_xml(INTERFACE_XML)?;
+    let interface_info = node_info
+        .lookup_interface(MAIN_APP_ID)
+        .expect("interface is described in INTERFACE_XML");
+
+    connection
+        .register_object(OBJECT_PATH, &interface_info)
+        .method_call(move |_, _, _, _, method, parameters, invocation| {
+            debug!("D-Bus call: {}{}", method, parameters);
+            handle_method_call(method, parameters, invocation, runtime_data.clone());
+        })
+        .build()?;
+
+    info!("D-Bus interface exported at {}", OBJECT_PATH);
+    Ok(())
+}
+
+fn handle_method_call(
+    method: &str,
+    parameters: glib::Variant,
+    invocation: gio::DBusMethodInvocation,
+    runtime_data: Rc<RefCell<RuntimeData>>,
+) {
+    let request = match method {
+        "Show" => {
+            let (query,) = parameters.get::<(String,)>().unwrap_or_default();
+            if query.is_empty() {
+                Request::Show
+            } else {
+                Request::Query(query)
+            }
+        }
+        "Hide" => Request::Hide,
+        "Toggle" => Request::Toggle,
+        "Query" => {
+            let (text,) = parameters.get::<(String,)>().unwrap_or_default();
+            invocation.return_future_local(async move { query(text, runtime_data).await });
+            return;
+        }
+        "ListPlugins" => Request::ListPlugins,
+        _ => {
+            invocation.return_dbus_error(
+                "org.freedesktop.DBus.Error.UnknownMethod",
+                &format!("Unknown method {}", method),
+            );
+            return;
+        }
+    };
+
+    match handle_request(request, runtime_data) {
+        Response::Plugins(plugins) => {
+            let plugins = plugins
+                .into_iter()
+                .map(|plugin| (plugin.pid, plugin.name, plugin.fallback, plugin.busy))
+                .collect::<Vec<_>>();
+            invocation.return_value(Some(&(plugins,).to_variant()));
+        }
+        Response::Error(e) => invocation.return_dbus_error(ERROR_FAILED, &e),
+        _ => invocation.return_value(None),
+    }
+}
+
+/// Runs query through the host and answers with hits once all plugins finished.
+/// Query of the entry is asked again after that, entry itself is not touched,
+/// so visible window shows results of D-Bus query only for the time it runs
+async fn query(
+    text: String,
+    runtime_data: Rc<RefCell<RuntimeData>>,
+) -> Result<Option<glib::Variant>, glib::Error> {
+    let Some(widgets) = runtime_data.borrow().widgets.clone() else {
+        return Err(glib::Error::new(
+            gio::DBusError::Failed,
+            "Runner UI is not built yet",
+        ));
+    };
+    let host = runtime_data.borrow().host.clone();
+    host.query(&text);
+
+    let mut waited = Duration::ZERO;
+    loop {
+        glib::timeout_future(QUERY_POLL).await;
+        waited += QUERY_POLL;
+
+        if !host.is_busy() {
+            break;
+        }
+        if waited >= QUERY_TIMEOUT {
+            warn!(
+                "D-Bus query {:?} timed out, answering with partial hits",
+                text
+            );
+            break;
+        }
+    }
+
+    // user typed into the window meanwhile, results are of their query now
+    if host.current_query() != text {
+        return Err(glib::Error::new(
+            gio::DBusError::Failed,
+            "Query was replaced by user input",
+        ));
+    }
+
+    let hits = host
+        .results()
+        .into_iter()
+        .map(|PluginHit { pid, hit }| {
+            let plugin = host
+                .plugin(pid)
+                .map(|plugin| plugin.name())
+                .unwrap_or_default();
+            let title = plain_text(hit.title, hit.use_pango);
+            let description = hit
+                .description
+                .map(|description| plain_text(description, hit.use_pango))
+                .unwrap_or_default();
+            (plugin, hit.id.to_string(), title, description)
+        })
+        .collect::<Vec<_>>();
+    host.query(&widgets.entry.text());
+
+    Ok(Some((hits,).to_variant()))
+}
+
+fn emit(application: &gtk::Application, signal: &str, parameters: glib::Variant) {
+    let Some(connection) = application.dbus_connection() else {
+        return;
+    };
+    if let Err(e) =
+        connection.emit_signal(None, OBJECT_PATH, MAIN_APP_ID, signal, Some(&parameters))
+    {
+        warn!("Failed to emit {} signal: {}", signal, e);
+    }
+}
+
+pub fn emit_activated(application: &gtk::Application, plugin: &str, hit: &str) {
+    emit(application, "Activated", (plugin, hit).to_variant());
+}
+
+pub fn emit_visibility_changed(application: &gtk::Application, visible: bool) {
+    emit(application, "VisibilityChanged", (visible,).to_variant());
+}
@@ -0,0 +1,173 @@
+use std::{
+    cell::{Cell, RefCell},
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
+    rc::Rc,
+};
+
+use gtk::{gio, glib, prelude::*};
+#[allow(unused_imports)]
+use log::*;
+
+use crate::types::{ghit::GHit, gshow_more::GShowMore, RuntimeData};
+
+/// Plugin whose group `item` belongs to. Works for every item of grouped model
+pub fn plugin_pid(item: &glib::Object) -> Option<u64> {
+    if let Some(ghit) = item.downcast_ref::<GHit>() {
+        Some(ghit.get_plugin_pid())
+    } else {
+        item.downcast_ref::<GShowMore>()
+            .map(|more| more.get_plugin_pid())
+    }
+}
+
+/// Grouped view of `RuntimeData::hit_store`.
+///
+/// Hits are copied into own store ordered by group and capped. Sections of the model
+/// are groups, so `gtk::ListView` draws header for every group and never focuses it
+#[derive(Clone)]
+pub struct Groups {
+    inner: Rc<GroupsInner>,
+}
+
+struct GroupsInner {
+    store: gio::ListStore,
+    model: gtk::SortListModel,
+    /// Position of plugin group
+    ranks: Rc<RefCell<HashMap<u64, usize>>>,
+    expanded: RefCell<HashSet<u64>>,
+    rebuild_scheduled: Cell<bool>,
+}
+
+impl Groups {
+    pub fn new() -> Self {
+        let store = gio::ListStore::new::<glib::Object>();
+        let ranks: Rc<RefCell<HashMap<u64, usize>>> = Default::default();
+
+        let section_sorter = gtk::CustomSorter::new(glib::clone!(
+            #[strong]
+            ranks,
+            move |a, b| {
+                let ranks = ranks.borrow();
+                let rank = |item: &glib::Object| {
+                    plugin_pid(item)
+                        .and_then(|pid| ranks.get(&pid).copied())
+                        .unwrap_or(usize::MAX)
+                };
+                rank(a).cmp(&rank(b)).into()
+            }
+        ));
+        // store is already ordered, section sorter only splits it into groups
+        let model = gtk::SortListModel::new(Some(store.clone()), None::<gtk::Sorter>);
+        model.set_section_sorter(Some(&section_sorter));
+
+        Self {
+            inner: Rc::new(GroupsInner {
+                store,
+                model,
+                ranks,
+                expanded: Default::default(),
+                rebuild_scheduled: Default::default(),
+            }),
+        }
+    }
+
+    pub fn model(&self) -> &gtk::SortListModel {
+        &self.inner.model
+    }
+
+    /// Hits come one by one, so groups are rebuilt once per main loop iteration
+    pub fn schedule_rebuild(&self, runtime_data: Rc<RefCell<RuntimeData>>) {
+        if self.inner.rebuild_scheduled.replace(true) {
+            return;
+        }
+
+        glib::idle_add_local_once(glib::clone!(
+            #[strong(rename_to = groups)]
+            self,
+            move || {
+                groups.inner.rebuild_scheduled.set(false);
+                groups.rebuild(&runtime_data.borrow());
+            }
+        ));
+    }
+
+    /// Shows all hits of the group. Rebuilds right away so caller can select revealed hit
+    pub fn expand(&self, plugin_pid: u64, runtime_data: &RuntimeData) {
+        self.inner.expanded.borrow_mut().insert(plugin_pid);
+        self.rebuild(runtime_data);
+    }
+
+    /// Called for every new query
+    pub fn collapse_all(&self) {
+        self.inner.expanded.borrow_mut().clear();
+    }
+
+    fn rebuild(&self, runtime_data: &RuntimeData) {
+        let mut order = Vec::new();
+        let mut groups: HashMap<u64, Vec<glib::Object>> = HashMap::new();
+        for item in runtime_data.hit_store.iter::<glib::Object>().flatten() {
+            let Some(pid) = plugin_pid(&item) else {
+                continue;
+            };
+            groups
+                .entry(pid)
+                .or_insert_with(|| {
+                    order.push(pid);
+                    Vec::new()
+                })
+                .push(item);
+        }
+
+        // stable, so groups of the same priority keep order of the first hit
+        let config = runtime_data.host.config();
+        order.sort_by_cached_key(|pid| {
+            let priority = runtime_data
+                .host
+                .plugin(*pid)
+                .map(|plugin| config.plugin(&plugin.name()).priority)
+                .unwrap_or_default();
+            Reverse(priority)
+        });
+
+        let limit = config.group_limit;
+        let expanded = self.inner.expanded.borrow();
+        let mut items = Vec::new();
+        for pid in &order {
+            let mut group = groups.remove(pid).unwrap_or_default();
+            if !expanded.contains(pid) && group.len() > limit {
+                let hidden = group.split_off(limit);
+                group.push(GShowMore::new(*pid, hidden.len() as u32).upcast());
+            }
+            items.extend(group);
+        }
+
+        self.inner.ranks.replace(
+            order
+                .into_iter()
+                .enumerate()
+                .map(|(rank, pid)| (pid, rank))
+                .collect(),
+        );
+
+        // only changed tail is replaced, so list keeps its rows and selection
+        let store = &self.inner.store;
+        let unchanged = store
+            .iter::<glib::Object>()
+            .flatten()
+            .zip(&items)
+            .take_while(|(old, new)| old == *new)
+            .count();
+        store.splice(
+            unchanged as u32,
+            store.n_items() - unchanged as u32,
+            &items[unchanged..],
+        );
+    }
+}
+
+impl Default for Groups {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,4 +1,4 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::RefCell, rc::Rc, time::Duration};
 
 use gtk::{
     gdk::Key,
@@ -10,14 +10,20 @@ use gtk::{
 use gtk_layer_shell::LayerShell;
 #[allow(unused_imports)]
 use log::*;
-use unirun_if::{
-    package::{Command, Package, Payload},
-    socket::Stream,
-};
 
 use crate::{
-    types::{ghit::GHit, RuntimeData},
-    utils::{handle_selection_activation, on_entry_changed},
+    banner::build_error_banner,
+    dbus,
+    groups::{self, Groups},
+    icons,
+    preview::{build_preview_pane, show_preview},
+    stats::{build_stats_overlay, show_stats},
+    status::{build_status_bar, set_status},
+    types::{ghit::GHit, gshow_more::GShowMore, hit_row::HitRow, RuntimeData, Widgets},
+    utils::{
+        close_window, copy_hit, handle_selection_activation, load_more, on_entry_changed, pop_view,
+        request_preview,
+    },
     MAIN_WINDOW_TITLE,
 };
 
@@ -43,35 +49,12 @@ fn build_window(app: impl IsA<gtk::Application>) -> gtk::ApplicationWindow {
         window.set_keyboard_mode(KeyboardMode::OnDemand); // TODO move to config
     }
 
-    fn connect_window_key_press_events(
-        widget: impl WidgetExt,
-        event_controller_key: gtk::EventControllerKey,
-    ) {
-        connect_key_press_events(widget, event_controller_key, move |keyval| match keyval {
-            Key::Escape => {
-                glib::spawn_future_local(async move {
-                    Stream::new_future()
-                        .await
-                        .unwrap()
-                        .write_future(Package::new(Payload::Command(Command::Quit)))
-                        .await
-                        .unwrap()
-                });
-                glib::Propagation::Stop
-            }
-            _ => glib::Propagation::Proceed,
-        });
-    }
-
     let window = gtk::ApplicationWindow::new(&app);
     window.set_title(Some(MAIN_WINDOW_TITLE));
     window.set_default_size(650, 500); // TODO move to config?
     #[cfg(feature = "default")]
     init_layer_shell(window.clone());
 
-    let window_eck = gtk::EventControllerKey::new();
-    connect_window_key_press_events(window.clone(), window_eck);
-
     window
 }
 
@@ -88,17 +71,6 @@ where
             widget.clone(),
             event_controller_key,
             move |keyval| match keyval {
-                Key::Escape => {
-                    glib::spawn_future_local(async move {
-                        Stream::new_future()
-                            .await
-                            .unwrap()
-                            .write_future(Package::new(Payload::Command(Command::Quit)))
-                            .await
-                            .unwrap()
-                    });
-                    glib::Propagation::Stop
-                }
                 Key::Down | Key::Up => {
                     widget.emit_move_focus(if keyval == Key::Down {
                         gtk::DirectionType::TabForward
@@ -123,58 +95,306 @@ where
     entry
 }
 
-fn build_main_list<A>(model: impl IsA<gio::ListModel>, on_activate: A) -> gtk::ListBox
+/// Delay before asking plugin for preview, so it's not requested for every row user goes through
+const PREVIEW_DEBOUNCE: Duration = Duration::from_millis(150);
+
+fn connect_preview(
+    selection: &gtk::SingleSelection,
+    pane: &gtk::ScrolledWindow,
+    runtime_data: Rc<RefCell<RuntimeData>>,
+) {
+    let pending: Rc<RefCell<Option<glib::SourceId>>> = Default::default();
+    // plugin can update preview of selected hit
+    let preview_handler: Rc<RefCell<Option<(GHit, glib::SignalHandlerId)>>> = Default::default();
+
+    selection.connect_selected_item_notify(clone!(
+        #[strong]
+        pane,
+        move |selection| {
+            if let Some(source) = pending.take() {
+                source.remove();
+            }
+            if let Some((ghit, handler)) = preview_handler.take() {
+                ghit.disconnect(handler);
+            }
+
+            let Some(ghit) = selection.selected_item().and_downcast::<GHit>() else {
+                show_preview(&pane, None);
+                return;
+            };
+
+            let handler = ghit.connect_notify_local(
+                Some("preview"),
+                clone!(
+                    #[weak]
+                    pane,
+                    move |ghit, _| {
+                        if let Some(preview) = ghit.get_preview() {
+                            show_preview(&pane, Some(&preview));
+                        }
+                    }
+                ),
+            );
+            preview_handler.replace(Some((ghit.clone(), handler)));
+
+            if let Some(preview) = ghit.get_preview() {
+                show_preview(&pane, Some(&preview));
+                return;
+            }
+
+            let source = glib::timeout_add_local_once(
+                PREVIEW_DEBOUNCE,
+                clone!(
+                    #[strong]
+                    pending,
+                    #[strong]
+                    selection,
+                    #[strong]
+                    pane,
+                    #[strong]
+                    runtime_data,
+                    move || {
+                        pending.take();
+
+                        glib::spawn_future_local(async move {
+                            let preview = request_preview(&ghit, runtime_data).await;
+                            if selection.selected_item().as_ref() == Some(ghit.upcast_ref()) {
+                                show_preview(&pane, preview.as_ref());
+                            }
+                        });
+                    }
+                ),
+            );
+            pending.replace(Some(source));
+        }
+    ));
+}
+
+fn build_main_list<A, M>(
+    selection: &gtk::SingleSelection,
+    on_activate: A,
+    on_load_more: M,
+) -> gtk::ListView
 where
-    A: Fn(&gtk::ListBoxRow) + 'static,
+    A: Fn(u32) + 'static,
+    M: Fn() + 'static,
 {
-    let main_list = gtk::ListBox::builder()
-        .selection_mode(gtk::SelectionMode::Single)
-        .build();
-    main_list.bind_model(Some(&model), move |hit_row| {
-        hit_row
-            .clone()
-            .downcast::<GHit>()
-            .expect("Can't downcast glib::Object to GHit")
-            .into()
+    fn hit_row(list_item: &glib::Object) -> HitRow {
+        list_item
+            .downcast_ref::<gtk::ListItem>()
+            .and_then(|list_item| list_item.child())
+            .and_downcast::<HitRow>()
+            .expect("Can't downcast gtk::Widget to HitRow")
+    }
+
+    let factory = gtk::SignalListItemFactory::new();
+    factory.connect_setup(|_, list_item| {
+        list_item
+            .downcast_ref::<gtk::ListItem>()
+            .expect("Can't downcast glib::Object to gtk::ListItem")
+            .set_child(Some(&HitRow::new()))
+    });
+    factory.connect_bind(|_, list_item| {
+        let item = list_item
+            .downcast_ref::<gtk::ListItem>()
+            .and_then(|list_item| list_item.item())
+            .expect("List item has no item");
+        if let Some(ghit) = item.downcast_ref::<GHit>() {
+            hit_row(list_item).bind(ghit);
+        } else if let Some(more) = item.downcast_ref::<GShowMore>() {
+            hit_row(list_item).bind_show_more(more);
+        }
     });
+    factory.connect_unbind(|_, list_item| hit_row(list_item).unbind());
 
-    model.connect_items_changed(clone!(
-        #[strong]
-        main_list,
-        move |_, _, _, _| main_list.select_row(main_list.row_at_index(0).as_ref())
-    ));
+    let main_list = gtk::ListView::builder()
+        .model(selection)
+        .factory(&factory)
+        .single_click_activate(true)
+        .build();
 
-    main_list.connect_row_activated(move |_, row| on_activate(row));
+    // only when list is refilled, so selection stays in place when next page comes
+    selection.connect_items_changed(|selection, position, _, _| {
+        if position == 0 && selection.n_items() > 0 {
+            selection.set_selected(0);
+        }
+    });
 
-    main_list.connect_move_cursor(clone!(
-        #[strong]
-        model,
-        move |_self, _, count, _, _| {
-            if let Some(row) = _self.selected_row() {
-                if row.index() == 0 && count == -1 {
-                    _self.emit_move_focus(gtk::DirectionType::TabBackward);
-                } else if row.index() == model.n_items() as i32 - 1 && count == 1 {
-                    _self.emit_move_focus(gtk::DirectionType::TabForward);
+    main_list.connect_activate(move |_, position| on_activate(position));
+
+    // ListView has no `move-cursor` signal so focus is moved out of the list by hand.
+    // Capture phase to see selection before list moves it
+    let main_list_eck = gtk::EventControllerKey::builder()
+        .propagation_phase(gtk::PropagationPhase::Capture)
+        .build();
+    connect_key_press_events(
+        main_list.clone(),
+        main_list_eck,
+        clone!(
+            #[strong]
+            main_list,
+            #[strong]
+            selection,
+            move |keyval| {
+                let selected = selection.selected();
+                let n_items = selection.n_items();
+                match keyval {
+                    Key::Up if selected == 0 => {
+                        main_list.emit_move_focus(gtk::DirectionType::TabBackward);
+                        glib::Propagation::Stop
+                    }
+                    Key::Down if n_items > 0 && selected == n_items - 1 => {
+                        main_list.emit_move_focus(gtk::DirectionType::TabForward);
+                        glib::Propagation::Stop
+                    }
+                    Key::Page_Down if n_items > 0 && selected == n_items - 1 => {
+                        on_load_more();
+                        glib::Propagation::Stop
+                    }
+                    _ => glib::Propagation::Proceed,
                 }
             }
-        }
-    ));
+        ),
+    );
 
     main_list
 }
 
+/// Header of plugin group in grouped layout
+fn build_group_header_factory(runtime_data: Rc<RefCell<RuntimeData>>) -> gtk::ListItemFactory {
+    fn parts(list_header: &glib::Object) -> (gtk::ListHeader, gtk::Image, gtk::Label) {
+        let list_header = list_header
+            .downcast_ref::<gtk::ListHeader>()
+            .expect("Can't downcast glib::Object to gtk::ListHeader")
+            .clone();
+        let hbox = list_header
+            .child()
+            .expect("List header has no child")
+            .downcast::<gtk::Box>()
+            .expect("Can't downcast gtk::Widget to gtk::Box");
+        let image = hbox
+            .first_child()
+            .and_downcast::<gtk::Image>()
+            .expect("Group header has no image");
+        let label = hbox
+            .last_child()
+            .and_downcast::<gtk::Label>()
+            .expect("Group header has no label");
+        (list_header, image, label)
+    }
+
+    let factory = gtk::SignalListItemFactory::new();
+    factory.connect_setup(|_, list_header| {
+        let hbox = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+        hbox.append(&gtk::Image::builder().pixel_size(16).build());
+        hbox.append(
+            &gtk::Label::builder()
+                .xalign(0.0)
+                .css_classes(["heading"])
+                .build(),
+        );
+
+        list_header
+            .downcast_ref::<gtk::ListHeader>()
+            .expect("Can't downcast glib::Object to gtk::ListHeader")
+            .set_child(Some(&hbox));
+    });
+    factory.connect_bind(move |_, list_header| {
+        let (list_header, image, label) = parts(list_header);
+        let Some(pid) = list_header.item().as_ref().and_then(groups::plugin_pid) else {
+            return;
+        };
+        let Some(plugin) = runtime_data.borrow().host.plugin(pid) else {
+            return;
+        };
+
+        label.set_text(&plugin.name());
+        match plugin.icon() {
+            Some(icon) => {
+                icons::set_icon(
+                    &image,
+                    &icon,
+                    clone!(
+                        #[weak]
+                        list_header,
+                        #[upgrade_or]
+                        false,
+                        move |_| list_header.item().as_ref().and_then(groups::plugin_pid)
+                            == Some(pid)
+                    ),
+                );
+                image.set_visible(true);
+            }
+            None => image.set_visible(false),
+        }
+    });
+
+    factory.upcast()
+}
+
 pub fn build_ui(
     app: impl IsA<gtk::Application>,
     runtime_data: Rc<RefCell<RuntimeData>>,
 ) -> Result<(), glib::Error> {
+    let grouped = runtime_data.borrow().host.config().grouped;
+    let model: gio::ListModel = if grouped {
+        let groups = Groups::new();
+        runtime_data
+            .borrow()
+            .hit_store
+            .connect_items_changed(clone!(
+                #[strong]
+                groups,
+                #[strong]
+                runtime_data,
+                move |_, _, _, _| groups.schedule_rebuild(runtime_data.clone())
+            ));
+        runtime_data.borrow_mut().groups = Some(groups.clone());
+        groups.model().clone().upcast()
+    } else {
+        runtime_data.borrow().hit_store.clone().upcast()
+    };
+    let selection = gtk::SingleSelection::new(Some(model));
+
+    let activate = Rc::new(clone!(
+        #[strong]
+        selection,
+        #[strong]
+        runtime_data,
+        move |position: u32| {
+            let Some(item) = selection.item(position) else {
+                return;
+            };
+
+            if let Some(ghit) = item.downcast_ref::<GHit>() {
+                handle_selection_activation(ghit.clone(), runtime_data.clone());
+            } else if let Some(more) = item.downcast_ref::<GShowMore>() {
+                let groups = runtime_data.borrow().groups.clone();
+                if let Some(groups) = groups {
+                    groups.expand(more.get_plugin_pid(), &runtime_data.borrow());
+                    // first revealed hit takes place of this row
+                    selection.set_selected(position);
+                }
+            }
+        }
+    ));
+
     let main_list = build_main_list(
-        runtime_data.borrow().hit_store.clone(),
+        &selection,
+        clone!(
+            #[strong]
+            activate,
+            move |position| activate(position)
+        ),
         clone!(
             #[strong]
             runtime_data,
-            move |row| handle_selection_activation(row.index() as u32, runtime_data.clone())
+            move || load_more(runtime_data.clone())
         ),
     );
+    if grouped {
+        main_list.set_header_factory(Some(&build_group_header_factory(runtime_data.clone())));
+    }
 
     let entry = build_entry(
         clone!(
@@ -184,12 +404,11 @@ pub fn build_ui(
         ),
         clone!(
             #[strong]
-            main_list,
-            #[strong]
-            runtime_data,
+            selection,
             move || {
-                if let Some(row) = main_list.selected_row() {
-                    handle_selection_activation(row.index() as u32, runtime_data.clone())
+                let position = selection.selected();
+                if position != gtk::INVALID_LIST_POSITION {
+                    activate(position);
                 }
             }
         ),
@@ -203,15 +422,143 @@ pub fn build_ui(
         .focusable(true)
         .child(&main_list)
         .build();
+    scroll_window.connect_edge_reached(clone!(
+        #[strong]
+        runtime_data,
+        move |_, position| {
+            if position == gtk::PositionType::Bottom {
+                load_more(runtime_data.clone());
+            }
+        }
+    ));
+
+    let placeholder = gtk::Label::builder()
+        .label("Searching…")
+        .css_classes(["dim-label"])
+        .build();
+
+    // ListView has no placeholder of its own
+    let results = gtk::Stack::new();
+    results.add_named(&scroll_window, Some("results"));
+    results.add_named(&placeholder, Some("placeholder"));
+    results.set_visible_child_name("placeholder");
+    selection.connect_items_changed(clone!(
+        #[weak]
+        results,
+        move |selection, _, _, _| {
+            results.set_visible_child_name(if selection.n_items() > 0 {
+                "results"
+            } else {
+                "placeholder"
+            })
+        }
+    ));
+
+    let message = gtk::Label::builder()
+        .wrap(true)
+        .xalign(0.0)
+        .visible(false)
+        .build();
+
+    let breadcrumbs = gtk::Label::builder()
+        .xalign(0.0)
+        .ellipsize(gtk::pango::EllipsizeMode::Start)
+        .visible(false)
+        .build();
+
+    let status_bar = build_status_bar();
+    // plugins start before UI
+    for plugin in runtime_data.borrow().host.plugins() {
+        if let Some(status) = plugin.status() {
+            set_status(&status_bar, plugin.pid(), &plugin.name(), Some(&status));
+        }
+    }
+
+    let preview_pane = build_preview_pane();
+    connect_preview(&selection, &preview_pane, runtime_data.clone());
+
+    // TODO move to config? Vertical to show preview at the bottom
+    let results_box = gtk::Box::new(gtk::Orientation::Horizontal, 5);
+    results_box.append(&results);
+    results_box.append(&preview_pane);
+
+    let stats_overlay = build_stats_overlay();
+    let overlay = gtk::Overlay::builder().child(&results_box).build();
+    overlay.add_overlay(&stats_overlay);
+
+    let error_banner = build_error_banner();
 
     let vbox = gtk::Box::new(gtk::Orientation::Vertical, 5);
+    vbox.append(&error_banner);
+    vbox.append(&breadcrumbs);
     vbox.append(&entry.clone());
-    vbox.append(&scroll_window);
+    vbox.append(&overlay);
+    vbox.append(&status_bar);
+    vbox.append(&message);
 
     let window = build_window(app);
     window.set_child(Some(&vbox));
+
+    // Capture phase to get keys before entry does
+    let shortcuts_eck = gtk::EventControllerKey::builder()
+        .propagation_phase(gtk::PropagationPhase::Capture)
+        .build();
+    shortcuts_eck.connect_key_pressed(clone!(
+        #[strong]
+        entry,
+        #[strong]
+        selection,
+        #[strong]
+        runtime_data,
+        #[strong]
+        stats_overlay,
+        move |_, keyval, _, state| match keyval {
+            // Entry still copies its own selection
+            Key::c
+                if state.contains(gtk::gdk::ModifierType::CONTROL_MASK)
+                    && entry.selection_bounds().is_none() =>
+            {
+                if let Some(ghit) = selection.selected_item().and_downcast::<GHit>() {
+                    copy_hit(&ghit, &entry.clipboard());
+                }
+                glib::Propagation::Stop
+            }
+            Key::Escape => {
+                if !pop_view(runtime_data.clone()) {
+                    close_window(runtime_data.clone());
+                }
+                glib::Propagation::Stop
+            }
+            Key::BackSpace if entry.text().is_empty() && pop_view(runtime_data.clone()) => {
+                glib::Propagation::Stop
+            }
+            Key::F12 => {
+                show_stats(&stats_overlay, &runtime_data.borrow().host.stats());
+                stats_overlay.set_visible(!stats_overlay.is_visible());
+                glib::Propagation::Stop
+            }
+            _ => glib::Propagation::Proceed,
+        }
+    ));
+    window.add_controller(shortcuts_eck);
+    window.connect_visible_notify(|window| {
+        if let Some(app) = window.application() {
+            dbus::emit_visibility_changed(&app, window.is_visible());
+        }
+    });
     window.present();
 
+    runtime_data.borrow_mut().widgets = Some(Widgets {
+        window,
+        entry,
+        breadcrumbs,
+        message,
+        placeholder,
+        status_bar,
+        error_banner,
+        stats_overlay,
+    });
+
     info!("UI built and presented");
 
     Ok(())
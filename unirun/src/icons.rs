@@ -0,0 +1,123 @@
+use std::{
+    cell::RefCell,
+    collections::{HashMap, VecDeque},
+    error::Error,
+};
+
+use gtk::{gdk, gio, glib, prelude::*};
+#[allow(unused_imports)]
+use log::*;
+use unirun_if::package::Icon;
+
+const FALLBACK_ICON: &str = "image-missing";
+const CACHE_CAPACITY: usize = 256;
+
+/// Least recently used textures decoded from files, URIs and inline bytes
+struct TextureCache {
+    textures: HashMap<Icon, gdk::Texture>,
+    order: VecDeque<Icon>,
+}
+
+impl TextureCache {
+    fn new() -> Self {
+        Self {
+            textures: HashMap::with_capacity(CACHE_CAPACITY),
+            order: VecDeque::with_capacity(CACHE_CAPACITY),
+        }
+    }
+
+    fn get(&mut self, icon: &Icon) -> Option<gdk::Texture> {
+        let texture = self.textures.get(icon)?.clone();
+        if let Some(position) = self.order.iter().position(|i| i == icon) {
+            let icon = self.order.remove(position).unwrap();
+            self.order.push_back(icon);
+        }
+        Some(texture)
+    }
+
+    fn insert(&mut self, icon: Icon, texture: gdk::Texture) {
+        if self.textures.insert(icon.clone(), texture).is_some() {
+            return;
+        }
+
+        self.order.push_back(icon);
+        if self.order.len() > CACHE_CAPACITY {
+            if let Some(evicted) = self.order.pop_front() {
+                self.textures.remove(&evicted);
+            }
+        }
+    }
+}
+
+thread_local! {
+    static CACHE: RefCell<TextureCache> = RefCell::new(TextureCache::new());
+}
+
+async fn load_bytes(icon: &Icon) -> Result<glib::Bytes, Box<dyn Error>> {
+    let bytes = match icon {
+        Icon::Path(path) => gio::File::for_path(path).load_bytes_future().await?.0,
+        Icon::Uri(uri) => gio::File::for_uri(uri).load_bytes_future().await?.0,
+        Icon::Bytes { data, .. } => glib::Bytes::from_owned(glib::base64_decode(data)),
+        Icon::GIcon(_) | Icon::Name(_) => unreachable!("themed icons are not loaded by hand"),
+    };
+    Ok(bytes)
+}
+
+pub async fn load_texture(icon: &Icon) -> Result<gdk::Texture, Box<dyn Error>> {
+    if let Some(texture) = CACHE.with_borrow_mut(|cache| cache.get(icon)) {
+        return Ok(texture);
+    }
+
+    let bytes = load_bytes(icon).await?;
+    let texture = gio::spawn_blocking(move || gdk::Texture::from_bytes(&bytes))
+        .await
+        .map_err(|_| "texture decoding panicked")??;
+
+    CACHE.with_borrow_mut(|cache| cache.insert(icon.clone(), texture.clone()));
+    Ok(texture)
+}
+
+/// Sets `icon` to `image`. Icons that needs decoding are loaded off the main loop,
+/// `is_current` is checked before setting them as `image` could be reused for another icon meanwhile
+pub fn set_icon<F>(image: &gtk::Image, icon: &Icon, is_current: F)
+where
+    F: Fn(&Icon) -> bool + 'static,
+{
+    match icon {
+        Icon::Name(name) => image.set_icon_name(Some(name)),
+        Icon::GIcon(serialized) => match gio::Icon::for_string(serialized) {
+            Ok(gicon) => image.set_from_gicon(&gicon),
+            Err(e) => {
+                warn!("Failed to deserialize GIcon {:?}: {}", serialized, e);
+                image.set_icon_name(Some(FALLBACK_ICON));
+            }
+        },
+        Icon::Path(_) | Icon::Uri(_) | Icon::Bytes { .. } => {
+            if let Some(texture) = CACHE.with_borrow_mut(|cache| cache.get(icon)) {
+                image.set_paintable(Some(&texture));
+                return;
+            }
+
+            image.clear();
+            glib::spawn_future_local(glib::clone!(
+                #[weak]
+                image,
+                #[strong]
+                icon,
+                async move {
+                    let result = load_texture(&icon).await;
+                    if !is_current(&icon) {
+                        return;
+                    }
+                    match result {
+                        Ok(texture) => image.set_paintable(Some(&texture)),
+                        Err(e) => {
+                            warn!("Failed to load icon {:?}: {}", icon, e);
+                            image.set_icon_name(Some(FALLBACK_ICON));
+                        }
+                    }
+                }
+            ));
+        }
+    }
+}
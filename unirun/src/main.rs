@@ -1,8 +1,16 @@
+mod banner;
+mod control;
+mod dbus;
+mod groups;
 mod gui;
+mod icons;
+mod preview;
+mod stats;
+mod status;
 mod types;
 mod utils;
 
-use std::{cell::RefCell, fs, rc::Rc};
+use std::{cell::RefCell, env, fs, path::Path, rc::Rc};
 
 use gtk::{
     glib::{self, clone},
@@ -11,21 +19,39 @@ use gtk::{
 #[allow(unused_imports)]
 use log::*;
 use types::RuntimeData;
+use unirun_core::{config::Config, launch_plugins, metrics};
 use unirun_if::{
     constants::MAIN_APP_ID,
     package::{Command, Package, Payload},
-    path,
+    path, record,
     socket::Stream,
 };
-use utils::clear_entry_pool;
 
-use crate::utils::{build_socket_service, launch_plugins};
+use crate::utils::spawn_host_event_handler;
 
 pub const MAIN_WINDOW_TITLE: &str = "UniRun";
 
 fn main() -> Result<(), glib::Error> {
     env_logger::init();
 
+    // GTK doesn't know them
+    let mut args = env::args().collect::<Vec<_>>();
+    let print_stats = match args.iter().position(|arg| arg == "--stats") {
+        Some(position) => {
+            args.remove(position);
+            true
+        }
+        None => false,
+    };
+    if let Some(position) = args.iter().position(|arg| arg == "--record") {
+        args.remove(position);
+        if position < args.len() {
+            start_recording(Path::new(&args.remove(position)));
+        } else {
+            error!("--record needs path to file");
+        }
+    }
+
     ctrlc::set_handler(|| {
         info!("Ctrl-C shutdown");
         if let Err(e) = Stream::new()
@@ -37,21 +63,40 @@ fn main() -> Result<(), glib::Error> {
     })
     .expect("Error setting Ctrl-C handler");
 
-    let runtime_data = Rc::new(RefCell::new(RuntimeData::default()));
+    let runtime_data = Rc::new(RefCell::new(RuntimeData::new(Config::load())));
+    spawn_host_event_handler(runtime_data.clone());
 
-    let socket_service = build_socket_service(runtime_data.clone())?;
+    let socket_service = runtime_data.borrow().host.listen(&path::socket())?;
     socket_service.start();
 
+    let control_service = control::build_control_service(runtime_data.clone())?;
+    control_service.start();
+
     launch_plugins();
 
     let application = runtime_data.borrow().application.clone();
 
+    application.connect_startup(clone!(
+        #[strong]
+        runtime_data,
+        move |app| {
+            if let Err(e) = dbus::register(app, runtime_data.clone()) {
+                error!("Failed to export D-Bus interface: {}", e);
+            }
+        }
+    ));
+
     application.connect_activate(clone!(
         #[strong]
         runtime_data,
         move |app| {
             info!("Application activate");
 
+            if runtime_data.borrow().widgets.is_some() {
+                utils::show_window(runtime_data.clone());
+                return;
+            }
+
             if let Err(e) = gui::build_ui(app.clone(), runtime_data.clone()) {
                 error!("Failed to build UI: {}", e);
                 panic!("{}", e);
@@ -62,23 +107,23 @@ fn main() -> Result<(), glib::Error> {
     application.connect_shutdown(move |_| {
         info!("Application shutdown");
 
-        finalize_connections(runtime_data.clone());
+        if print_stats {
+            print!("{}", metrics::table(&runtime_data.borrow().host.stats()));
+        }
+
+        runtime_data.borrow().host.finalize();
         remove_socket_file();
     });
 
-    application.run();
+    application.run_with_args(&args);
 
     Ok(())
 }
 
-fn finalize_connections(runtime_data: Rc<RefCell<RuntimeData>>) {
-    clear_entry_pool(&mut runtime_data.borrow_mut());
-    let connections = runtime_data.borrow().connections.clone();
-    for stream in connections {
-        trace!("SENDING QUIT");
-        let _ = stream.write(Package::new(Payload::Command(Command::Quit)));
-
-        let _ = stream.read();
+fn start_recording(file: &Path) {
+    match record::start(file) {
+        Ok(()) => info!("Recording packages to {}", file.display()),
+        Err(e) => error!("Failed to record packages to {}: {}", file.display(), e),
     }
 }
 
@@ -86,11 +131,12 @@ fn finalize_connections(runtime_data: Rc<RefCell<RuntimeData>>) {
 // spawn new unirun instance kills all instances
 // this removes socket if there is another instance running
 fn remove_socket_file() {
-    let path = path::socket();
-    if path.exists() {
-        debug!("Removing socket file");
-        if let Err(e) = fs::remove_file(&path) {
-            error!("Failed to remove socket file: {}", e);
+    for path in [path::socket(), path::control_socket()] {
+        if path.exists() {
+            debug!("Removing socket file {}", path.display());
+            if let Err(e) = fs::remove_file(&path) {
+                error!("Failed to remove socket file: {}", e);
+            }
         }
     }
 }
@@ -0,0 +1,126 @@
+use std::error::Error;
+
+use gtk::{gio, glib, prelude::*};
+#[allow(unused_imports)]
+use log::*;
+use unirun_if::package::{Icon, Preview};
+
+use crate::icons;
+
+/// Only beginning of the file is read for [`Preview::File`]
+const FILE_PREVIEW_BYTES: usize = 1024 * 8;
+const FILE_PREVIEW_LINES: usize = 40;
+
+pub fn build_preview_pane() -> gtk::ScrolledWindow {
+    gtk::ScrolledWindow::builder()
+        .hscrollbar_policy(gtk::PolicyType::Never)
+        .width_request(260) // TODO move to config?
+        .visible(false)
+        .build()
+}
+
+fn build_text(text: &str, use_markup: bool) -> gtk::Label {
+    gtk::Label::builder()
+        .wrap(true)
+        .wrap_mode(gtk::pango::WrapMode::WordChar)
+        .xalign(0.0)
+        .yalign(0.0)
+        .selectable(true)
+        .use_markup(use_markup)
+        .label(text)
+        .build()
+}
+
+fn build_image(path: &str) -> gtk::Picture {
+    let picture = gtk::Picture::builder()
+        .can_shrink(true)
+        .valign(gtk::Align::Start)
+        .build();
+
+    let icon = Icon::Path(path.to_owned());
+    glib::spawn_future_local(glib::clone!(
+        #[weak]
+        picture,
+        async move {
+            match icons::load_texture(&icon).await {
+                Ok(texture) => picture.set_paintable(Some(&texture)),
+                Err(e) => warn!("Failed to load preview image {:?}: {}", icon, e),
+            }
+        }
+    ));
+
+    picture
+}
+
+async fn read_head(path: &str) -> Result<String, Box<dyn Error>> {
+    let stream = gio::File::for_path(path)
+        .read_future(glib::Priority::DEFAULT)
+        .await?;
+    let bytes = stream
+        .read_bytes_future(FILE_PREVIEW_BYTES, glib::Priority::DEFAULT)
+        .await?;
+
+    Ok(String::from_utf8_lossy(&bytes)
+        .lines()
+        .take(FILE_PREVIEW_LINES)
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+fn build_file(path: &str) -> gtk::Label {
+    let label = build_text("", false);
+    label.add_css_class("monospace");
+    label.set_wrap_mode(gtk::pango::WrapMode::Char);
+
+    let path = path.to_owned();
+    glib::spawn_future_local(glib::clone!(
+        #[weak]
+        label,
+        async move {
+            match read_head(&path).await {
+                Ok(head) => label.set_text(&head),
+                Err(e) => warn!("Failed to read preview file {:?}: {}", path, e),
+            }
+        }
+    ));
+
+    label
+}
+
+fn build_metadata(pairs: &[(String, String)]) -> gtk::Grid {
+    let grid = gtk::Grid::builder()
+        .row_spacing(4)
+        .column_spacing(12)
+        .valign(gtk::Align::Start)
+        .build();
+
+    for (row, (key, value)) in pairs.iter().enumerate() {
+        let key = build_text(key, false);
+        key.add_css_class("dim-label");
+        grid.attach(&key, 0, row as i32, 1, 1);
+        grid.attach(&build_text(value, false), 1, row as i32, 1, 1);
+    }
+
+    grid
+}
+
+/// Replaces pane content with `preview`. Pane is hidden if there is nothing to show
+pub fn show_preview(pane: &gtk::ScrolledWindow, preview: Option<&Preview>) {
+    let Some(preview) = preview else {
+        pane.set_child(gtk::Widget::NONE);
+        pane.set_visible(false);
+        return;
+    };
+
+    let child: gtk::Widget = match preview {
+        Preview::Text { text, use_pango } => build_text(text, *use_pango).upcast(),
+        Preview::Image(path) => build_image(path).upcast(),
+        Preview::File(path) => build_file(path).upcast(),
+        Preview::Metadata(pairs) => build_metadata(pairs).upcast(),
+    };
+    child.set_margin_start(8);
+    child.set_margin_end(8);
+
+    pane.set_child(Some(&child));
+    pane.set_visible(true);
+}
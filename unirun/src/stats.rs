@@ -0,0 +1,18 @@
+use unirun_core::metrics;
+use unirun_if::control::PluginStats;
+
+/// Debug overlay with metrics of plugins, toggled by F12
+pub fn build_stats_overlay() -> gtk::Label {
+    gtk::Label::builder()
+        .halign(gtk::Align::End)
+        .valign(gtk::Align::End)
+        .xalign(0.0)
+        .css_classes(["osd", "monospace"])
+        .can_target(false)
+        .visible(false)
+        .build()
+}
+
+pub fn show_stats(overlay: &gtk::Label, stats: &[PluginStats]) {
+    overlay.set_text(metrics::table(stats).trim_end());
+}
@@ -0,0 +1,95 @@
+use gtk::prelude::*;
+use unirun_if::package::{Severity, Status};
+
+/// One row per plugin with status
+pub fn build_status_bar() -> gtk::Box {
+    gtk::Box::builder()
+        .orientation(gtk::Orientation::Vertical)
+        .spacing(2)
+        .visible(false)
+        .build()
+}
+
+fn find_row(bar: &gtk::Box, plugin_pid: u64) -> Option<gtk::Box> {
+    let name = plugin_pid.to_string();
+    let mut child = bar.first_child();
+    while let Some(widget) = child {
+        if widget.widget_name() == name {
+            return widget.downcast().ok();
+        }
+        child = widget.next_sibling();
+    }
+    None
+}
+
+fn build_row(plugin_pid: u64) -> gtk::Box {
+    let row = gtk::Box::builder()
+        .orientation(gtk::Orientation::Horizontal)
+        .spacing(8)
+        .name(plugin_pid.to_string())
+        .build();
+    row.append(
+        &gtk::Label::builder()
+            .xalign(0.0)
+            .hexpand(true)
+            .ellipsize(gtk::pango::EllipsizeMode::End)
+            .build(),
+    );
+    row.append(
+        &gtk::ProgressBar::builder()
+            .valign(gtk::Align::Center)
+            .width_request(120)
+            .show_text(true)
+            .build(),
+    );
+    row
+}
+
+/// Replaces status of the plugin. `None` removes its row
+pub fn set_status(bar: &gtk::Box, plugin_pid: u64, plugin_name: &str, status: Option<&Status>) {
+    let row = find_row(bar, plugin_pid);
+
+    match (row, status) {
+        (Some(row), None) => bar.remove(&row),
+        (row, Some(status)) => {
+            let row = row.unwrap_or_else(|| {
+                let row = build_row(plugin_pid);
+                bar.append(&row);
+                row
+            });
+
+            let label = row
+                .first_child()
+                .and_downcast::<gtk::Label>()
+                .expect("Status row has no label");
+            label.set_text(&format!("{}: {}", plugin_name, status.message));
+            for (severity, class) in [(Severity::Warning, "warning"), (Severity::Error, "error")] {
+                if status.severity == severity {
+                    label.add_css_class(class);
+                } else {
+                    label.remove_css_class(class);
+                }
+            }
+
+            let progress_bar = row
+                .last_child()
+                .and_downcast::<gtk::ProgressBar>()
+                .expect("Status row has no progress bar");
+            match status.progress {
+                Some(progress) => {
+                    progress_bar.set_fraction(if progress.total > 0 {
+                        progress.done as f64 / progress.total as f64
+                    } else {
+                        0.0
+                    });
+                    progress_bar.set_text(Some(&format!("{} / {}", progress.done, progress.total)));
+                    progress_bar.set_visible(true);
+                }
+                None => progress_bar.set_visible(false),
+            }
+        }
+        (None, None) => {}
+    }
+
+    bar.set_visible(bar.first_child().is_some());
+}
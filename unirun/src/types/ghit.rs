@@ -4,9 +4,8 @@ use gtk::{
     prelude::{ObjectExt, ToValue},
 };
 use std::cell::{Cell, RefCell};
-use unirun_if::package::{Hit, HitId};
-
-use crate::utils::{build_image, build_label};
+use unirun_core::markup;
+use unirun_if::package::{Highlight, Hit, HitId, Icon, Preview};
 
 mod imp {
     use super::*;
@@ -16,8 +15,11 @@ mod imp {
         id: RefCell<String>,
         title: RefCell<String>,
         description: RefCell<Option<String>>,
-        icon: RefCell<Option<String>>,
+        icon: RefCell<Option<Icon>>,
         use_pango: Cell<bool>,
+        copy: RefCell<Option<String>>,
+        preview: RefCell<Option<Preview>>,
+        highlight: RefCell<Option<Highlight>>,
         plugin_pid: Cell<u64>,
     }
 
@@ -38,8 +40,11 @@ mod imp {
                     glib::ParamSpecString::builder("id").build(),
                     glib::ParamSpecString::builder("title").build(),
                     glib::ParamSpecString::builder("description").build(),
-                    glib::ParamSpecString::builder("icon").build(),
+                    glib::ParamSpecBoxed::builder::<Icon>("icon").build(),
                     glib::ParamSpecBoolean::builder("use-pango").build(),
+                    glib::ParamSpecString::builder("copy").build(),
+                    glib::ParamSpecBoxed::builder::<Preview>("preview").build(),
+                    glib::ParamSpecBoxed::builder::<Highlight>("highlight").build(),
                     glib::ParamSpecUInt64::builder("plugin-pid").build(),
                 ]
             })
@@ -82,6 +87,27 @@ mod imp {
                             .expect("type conformity checked by `Object::set_property`"),
                     );
                 }
+                "copy" => {
+                    self.copy.replace(
+                        value
+                            .get()
+                            .expect("type conformity checked by `Object::set_property`"),
+                    );
+                }
+                "preview" => {
+                    self.preview.replace(
+                        value
+                            .get()
+                            .expect("type conformity checked by `Object::set_property`"),
+                    );
+                }
+                "highlight" => {
+                    self.highlight.replace(
+                        value
+                            .get()
+                            .expect("type conformity checked by `Object::set_property`"),
+                    );
+                }
                 "plugin-pid" => {
                     self.plugin_pid.replace(
                         value
@@ -100,6 +126,9 @@ mod imp {
                 "description" => self.description.borrow().to_value(),
                 "icon" => self.icon.borrow().to_value(),
                 "use-pango" => self.use_pango.get().to_value(),
+                "copy" => self.copy.borrow().to_value(),
+                "preview" => self.preview.borrow().to_value(),
+                "highlight" => self.highlight.borrow().to_value(),
                 "plugin-pid" => self.plugin_pid.get().to_value(),
                 _ => unimplemented!(),
             }
@@ -145,11 +174,11 @@ impl GHit {
         self.set_property("description", value)
     }
 
-    pub fn get_icon(&self) -> Option<String> {
+    pub fn get_icon(&self) -> Option<Icon> {
         self.property("icon")
     }
 
-    pub fn set_icon(&self, value: Option<&str>) {
+    pub fn set_icon(&self, value: Option<Icon>) {
         self.set_property("icon", value)
     }
 
@@ -161,6 +190,55 @@ impl GHit {
         self.set_property("use-pango", value)
     }
 
+    pub fn get_copy(&self) -> Option<String> {
+        self.property("copy")
+    }
+
+    pub fn set_copy(&self, value: Option<&str>) {
+        self.set_property("copy", value)
+    }
+
+    pub fn get_preview(&self) -> Option<Preview> {
+        self.property("preview")
+    }
+
+    pub fn set_preview(&self, value: Option<Preview>) {
+        self.set_property("preview", value)
+    }
+
+    pub fn get_highlight(&self) -> Option<Highlight> {
+        self.property("highlight")
+    }
+
+    pub fn set_highlight(&self, value: Option<Highlight>) {
+        self.set_property("highlight", value)
+    }
+
+    /// `text` without markup if hit uses pango
+    pub fn plain_text(&self, text: String) -> String {
+        markup::plain_text(text, self.get_use_pango())
+    }
+
+    /// Text that goes to clipboard. `title` without markup if plugin didn't set `copy`
+    pub fn copy_text(&self) -> String {
+        self.get_copy()
+            .unwrap_or_else(|| self.plain_text(self.get_title()))
+    }
+
+    /// Replaces all fields with ones of `hit`. Bound rows get single notification per property
+    pub fn update(&self, hit: Hit) {
+        let _guard = self.freeze_notify();
+
+        self.set_id(&hit.id.to_string());
+        self.set_title(&hit.title);
+        self.set_description(hit.description.as_deref());
+        self.set_icon(hit.icon);
+        self.set_use_pango(hit.use_pango);
+        self.set_copy(hit.copy.as_deref());
+        self.set_preview(hit.preview);
+        self.set_highlight(hit.highlight);
+    }
+
     pub fn get_plugin_pid(&self) -> u64 {
         self.property("plugin-pid")
     }
@@ -179,12 +257,7 @@ impl Default for GHit {
 impl From<Hit> for GHit {
     fn from(value: Hit) -> Self {
         let item = Self::new();
-
-        item.set_id(&value.id.to_string());
-        item.set_title(&value.title);
-        item.set_description(value.description.as_deref());
-        item.set_icon(value.icon.as_deref());
-        item.set_use_pango(value.use_pango);
+        item.update(value);
 
         // TODO Handle plugin-pid if needed
 
@@ -200,44 +273,10 @@ impl From<GHit> for Hit {
             description: val.get_description(),
             icon: val.get_icon(),
             use_pango: val.get_use_pango(),
+            copy: val.get_copy(),
+            preview: val.get_preview(),
+            highlight: val.get_highlight(),
+            no_cache: false,
         }
     }
 }
-
-impl From<GHit> for gtk::Widget {
-    fn from(value: GHit) -> Self {
-        use gtk::prelude::BoxExt;
-
-        let hbox = gtk::Box::builder()
-            .orientation(gtk::Orientation::Horizontal)
-            .height_request(36)
-            .spacing(4)
-            .build();
-
-        let hit_box = gtk::Box::builder()
-            .orientation(gtk::Orientation::Horizontal)
-            .spacing(12)
-            .build();
-
-        if let Some(icon) = value.get_icon() {
-            hit_box.append(&build_image(&icon));
-        }
-
-        let vbox = gtk::Box::builder()
-            .orientation(gtk::Orientation::Vertical)
-            .hexpand(true)
-            .vexpand(true)
-            .build();
-
-        vbox.append(&build_label(value.get_use_pango(), &value.get_title()));
-
-        if let Some(desc) = value.get_description() {
-            vbox.append(&build_label(value.get_use_pango(), &desc));
-        }
-
-        hit_box.append(&vbox);
-        hbox.append(&hit_box);
-
-        hbox.into()
-    }
-}
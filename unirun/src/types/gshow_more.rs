@@ -0,0 +1,44 @@
+/// Row closing capped group in grouped layout. Activating it shows the rest of the group
+use gtk::glib::{self, subclass::prelude::*};
+use std::cell::Cell;
+
+mod imp {
+    use super::*;
+
+    #[derive(Default)]
+    pub struct GShowMore {
+        pub plugin_pid: Cell<u64>,
+        pub hidden: Cell<u32>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for GShowMore {
+        const NAME: &'static str = "GShowMore";
+
+        type Type = super::GShowMore;
+    }
+
+    impl ObjectImpl for GShowMore {}
+}
+
+glib::wrapper! {
+    pub struct GShowMore(ObjectSubclass<imp::GShowMore>);
+}
+
+impl GShowMore {
+    pub fn new(plugin_pid: u64, hidden: u32) -> Self {
+        let obj: Self = glib::Object::new();
+        obj.imp().plugin_pid.set(plugin_pid);
+        obj.imp().hidden.set(hidden);
+        obj
+    }
+
+    pub fn get_plugin_pid(&self) -> u64 {
+        self.imp().plugin_pid.get()
+    }
+
+    /// Number of group hits behind this row
+    pub fn get_hidden(&self) -> u32 {
+        self.imp().hidden.get()
+    }
+}
@@ -0,0 +1,215 @@
+/// Row widget recycled by `gtk::ListView` factory. Shows [`GHit`] bound with [`HitRow::bind`]
+use gtk::{
+    glib::{self, subclass::prelude::*},
+    prelude::*,
+    subclass::prelude::*,
+};
+#[allow(unused_imports)]
+use log::*;
+use std::cell::RefCell;
+use unirun_if::package::Icon;
+
+use crate::{
+    icons,
+    types::{ghit::GHit, gshow_more::GShowMore},
+};
+
+mod imp {
+    use super::*;
+
+    #[derive(Default)]
+    pub struct HitRow {
+        pub image: gtk::Image,
+        pub title: gtk::Label,
+        pub description: gtk::Label,
+        pub notify_handler: RefCell<Option<(GHit, glib::SignalHandlerId)>>,
+        /// Icon shown right now. Async loaded icon is dropped if it doesn't match
+        pub icon: RefCell<Option<Icon>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for HitRow {
+        const NAME: &'static str = "HitRow";
+
+        type Type = super::HitRow;
+        type ParentType = gtk::Box;
+    }
+
+    impl ObjectImpl for HitRow {
+        fn constructed(&self) {
+            self.parent_constructed();
+
+            let obj = self.obj();
+            obj.set_orientation(gtk::Orientation::Horizontal);
+            obj.set_height_request(36);
+            obj.set_spacing(12);
+
+            self.image.set_pixel_size(32);
+
+            for label in [&self.title, &self.description] {
+                label.set_wrap_mode(gtk::pango::WrapMode::Char);
+                label.set_wrap(true);
+                label.set_xalign(0.0);
+                label.set_halign(gtk::Align::Start);
+                label.set_valign(gtk::Align::Center);
+                label.set_vexpand(true);
+            }
+
+            let vbox = gtk::Box::builder()
+                .orientation(gtk::Orientation::Vertical)
+                .hexpand(true)
+                .vexpand(true)
+                .build();
+            vbox.append(&self.title);
+            vbox.append(&self.description);
+
+            obj.append(&self.image);
+            obj.append(&vbox);
+        }
+    }
+
+    impl WidgetImpl for HitRow {}
+
+    impl BoxImpl for HitRow {}
+}
+
+glib::wrapper! {
+    pub struct HitRow(ObjectSubclass<imp::HitRow>)
+        @extends gtk::Box, gtk::Widget,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::Orientable;
+}
+
+impl HitRow {
+    pub fn new() -> Self {
+        glib::Object::new()
+    }
+
+    pub fn bind(&self, ghit: &GHit) {
+        self.update_labels(ghit);
+        self.update_icon(ghit);
+
+        let handler = ghit.connect_notify_local(
+            None,
+            glib::clone!(
+                #[weak(rename_to = row)]
+                self,
+                move |ghit, pspec| match pspec.name() {
+                    "title" | "description" | "use-pango" | "highlight" => row.update_labels(ghit),
+                    "icon" => row.update_icon(ghit),
+                    _ => {}
+                }
+            ),
+        );
+        self.imp()
+            .notify_handler
+            .replace(Some((ghit.clone(), handler)));
+    }
+
+    pub fn bind_show_more(&self, more: &GShowMore) {
+        let imp = self.imp();
+
+        Self::set_label_text(
+            &imp.title,
+            &format!("Show {} more", more.get_hidden()),
+            false,
+            &[],
+        );
+        imp.description.set_visible(false);
+        imp.image.set_visible(false);
+    }
+
+    pub fn unbind(&self) {
+        let imp = self.imp();
+
+        if let Some((ghit, handler)) = imp.notify_handler.take() {
+            ghit.disconnect(handler);
+        }
+        imp.icon.take();
+    }
+
+    /// Markup is parsed into attributes here so highlight can be added without breaking it
+    fn set_label_text(label: &gtk::Label, text: &str, use_pango: bool, ranges: &[(usize, usize)]) {
+        let (attributes, text) = if use_pango {
+            match gtk::pango::parse_markup(text, '\0') {
+                Ok((attributes, text, _)) => (attributes, text.to_string()),
+                Err(e) => {
+                    warn!("Failed to parse markup {:?}: {}", text, e);
+                    (gtk::pango::AttrList::new(), text.to_owned())
+                }
+            }
+        } else {
+            (gtk::pango::AttrList::new(), text.to_owned())
+        };
+
+        // pango works with byte indices and highlight is in chars
+        let byte_index = |char_index: usize| {
+            text.char_indices()
+                .nth(char_index)
+                .map_or(text.len(), |(i, _)| i) as u32
+        };
+        for &(start, end) in ranges {
+            let mut attribute = gtk::pango::AttrInt::new_weight(gtk::pango::Weight::Bold);
+            attribute.set_start_index(byte_index(start));
+            attribute.set_end_index(byte_index(end));
+            attributes.insert(attribute);
+        }
+
+        label.set_use_markup(false);
+        label.set_text(&text);
+        label.set_attributes(Some(&attributes));
+    }
+
+    fn update_labels(&self, ghit: &GHit) {
+        let imp = self.imp();
+        let use_pango = ghit.get_use_pango();
+        let highlight = ghit.get_highlight().unwrap_or_default();
+
+        Self::set_label_text(&imp.title, &ghit.get_title(), use_pango, &highlight.title);
+
+        match ghit.get_description() {
+            Some(description) => {
+                Self::set_label_text(
+                    &imp.description,
+                    &description,
+                    use_pango,
+                    &highlight.description,
+                );
+                imp.description.set_visible(true);
+            }
+            None => imp.description.set_visible(false),
+        }
+    }
+
+    fn update_icon(&self, ghit: &GHit) {
+        let imp = self.imp();
+        let icon = ghit.get_icon();
+        imp.icon.replace(icon.clone());
+
+        match icon {
+            Some(icon) => {
+                icons::set_icon(
+                    &imp.image,
+                    &icon,
+                    glib::clone!(
+                        #[weak(rename_to = row)]
+                        self,
+                        #[upgrade_or]
+                        false,
+                        move |icon| {
+                            let is_current = row.imp().icon.borrow().as_ref() == Some(icon);
+                            is_current
+                        }
+                    ),
+                );
+                imp.image.set_visible(true);
+            }
+            None => imp.image.set_visible(false),
+        }
+    }
+}
+
+impl Default for HitRow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,25 +1,46 @@
 pub mod ghit;
+pub mod gshow_more;
+pub mod hit_row;
 
-use crate::MAIN_APP_ID;
+use crate::{groups::Groups, MAIN_APP_ID};
 
 use ghit::GHit;
-use gtk::{gio, glib, Application};
-use unirun_if::socket::Stream;
+use gtk::{gio, Application};
+use unirun_core::{config::Config, PluginHost};
+
+/// Widgets that needs to be reached from outside of `build_ui`
+#[derive(Clone)]
+pub struct Widgets {
+    pub window: gtk::ApplicationWindow,
+    pub entry: gtk::SearchEntry,
+    pub breadcrumbs: gtk::Label,
+    pub message: gtk::Label,
+    /// Shown instead of results list while it is empty
+    pub placeholder: gtk::Label,
+    pub status_bar: gtk::Box,
+    pub error_banner: gtk::Revealer,
+    /// Plugin metrics shown over results
+    pub stats_overlay: gtk::Label,
+}
 
 pub struct RuntimeData {
     pub application: gtk::Application,
-    pub connections: Vec<Stream>,
-    pub entry_pool: Vec<glib::JoinHandle<()>>,
+    pub host: PluginHost,
+    /// Mirror of host results, filled from its events
     pub hit_store: gio::ListStore,
+    pub widgets: Option<Widgets>,
+    /// `None` unless grouped layout is enabled
+    pub groups: Option<Groups>,
 }
 
-impl Default for RuntimeData {
-    fn default() -> Self {
+impl RuntimeData {
+    pub fn new(config: Config) -> Self {
         Self {
             application: Application::new(Some(MAIN_APP_ID), Default::default()),
-            connections: Default::default(),
-            entry_pool: Default::default(),
+            host: PluginHost::new(config),
             hit_store: gio::ListStore::new::<GHit>(),
+            widgets: Default::default(),
+            groups: Default::default(),
         }
     }
 }
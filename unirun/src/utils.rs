@@ -1,148 +1,110 @@
-use std::{
-    cell::RefCell, env::current_exe, fs::read_dir, os::unix::fs::PermissionsExt, path::PathBuf,
-    process, rc::Rc,
-};
+use std::{cell::RefCell, error::Error, rc::Rc};
 
 use gtk::{
-    gio,
+    gdk, gio,
     glib::{self, clone},
     prelude::*,
 };
 #[allow(unused_imports)]
 use log::*;
+use unirun_core::{Event, PluginHit};
 use unirun_if::{
-    package::{Command, Hit, Package, Payload},
-    path,
-    socket::Stream,
+    constants::MAIN_APP_ID,
+    package::{Action, ClipboardContent, HitId, Preview},
 };
 
 use crate::{
+    banner, dbus, stats, status,
     types::{ghit::GHit, RuntimeData},
     MAIN_WINDOW_TITLE,
 };
 
-pub fn build_socket_service(
-    runtime_data: Rc<RefCell<RuntimeData>>,
-) -> Result<gio::SocketService, glib::Error> {
-    fn handle_new_connection(stream: Stream, runtime_data: Rc<RefCell<RuntimeData>>) {
-        fn handle_new_connection_from_self(stream: Stream, runtime_data: Rc<RefCell<RuntimeData>>) {
-            fn handle_socket_data(data: &Payload, runtime_data: Rc<RefCell<RuntimeData>>) {
-                if let Payload::Command(Command::Quit) = data {
-                    runtime_data.borrow().application.quit()
-                }
-            }
-
-            glib::spawn_future_local(async move {
-                let data = stream.read_future().await.unwrap_or_else(|e| {
-                    error!("{}", e);
-                    panic!("{}", e);
-                });
-
-                handle_socket_data(&data.payload, runtime_data);
-            });
+/// Reflects plugin host events in the window
+pub fn spawn_host_event_handler(runtime_data: Rc<RefCell<RuntimeData>>) {
+    let events = runtime_data.borrow().host.events();
+    glib::spawn_future_local(async move {
+        while let Ok(event) = events.recv().await {
+            handle_host_event(event, runtime_data.clone());
         }
+    });
+}
 
-        let creds = stream.credentials.unwrap_or_default();
-
-        let pid = creds.pid.expect("Failed to read process ID");
-        if std::process::id() == pid {
-            handle_new_connection_from_self(stream, runtime_data.clone());
-        } else {
-            runtime_data.borrow_mut().connections.push(stream.clone());
-            on_entry_changed("", runtime_data.clone());
+fn handle_host_event(event: Event, runtime_data: Rc<RefCell<RuntimeData>>) {
+    match event {
+        Event::Splice {
+            position,
+            removed,
+            added,
+        } => {
+            let ghits = added.into_iter().map(to_ghit).collect::<Vec<_>>();
+            // store handlers may borrow runtime_data
+            let hit_store = runtime_data.borrow().hit_store.clone();
+            hit_store.splice(position as u32, removed as u32, &ghits);
+        }
+        Event::Update { position, hit } => {
+            let hit_store = runtime_data.borrow().hit_store.clone();
+            if let Some(ghit) = hit_store.item(position as u32).and_downcast::<GHit>() {
+                ghit.update(hit.hit);
+            }
+        }
+        Event::Connected(pid) => debug!("Plugin {} connected", pid),
+        Event::Registered(_) => {
+            // name decides group priority
+            let groups = runtime_data.borrow().groups.clone();
+            if let Some(groups) = groups {
+                groups.schedule_rebuild(runtime_data);
+            }
+        }
+        Event::Disconnected(pid) => {
+            if let Some(widgets) = &runtime_data.borrow().widgets {
+                status::set_status(&widgets.status_bar, pid, "", None);
+            }
+        }
+        Event::Status { pid, status } => {
+            let runtime_data = runtime_data.borrow();
+            if let (Some(widgets), Some(plugin)) =
+                (&runtime_data.widgets, runtime_data.host.plugin(pid))
+            {
+                status::set_status(&widgets.status_bar, pid, &plugin.name(), status.as_ref());
+            }
+        }
+        Event::Error { pid, message } => show_plugin_error(&runtime_data.borrow(), pid, &message),
+        Event::Progress => {
+            let runtime_data = runtime_data.borrow();
+            update_placeholder(&runtime_data);
+            if let Some(widgets) = runtime_data
+                .widgets
+                .as_ref()
+                .filter(|widgets| widgets.stats_overlay.is_visible())
+            {
+                stats::show_stats(&widgets.stats_overlay, &runtime_data.host.stats());
+            }
         }
+        // Ctrl-C or SIGTERM, nothing to wait for
+        Event::Quit => runtime_data.borrow().application.quit(),
     }
-
-    let socket_path = path::socket();
-    let socket_service = gio::SocketService::new();
-
-    socket_service.add_address(
-        &gio::UnixSocketAddress::new(&socket_path),
-        gio::SocketType::Stream,
-        gio::SocketProtocol::Default,
-        glib::Object::NONE,
-    )?;
-
-    socket_service.connect_incoming(move |_, connection, _| {
-        handle_new_connection(Stream::from(connection.clone()), runtime_data.clone());
-        true
-    });
-
-    Ok(socket_service)
 }
 
-pub fn build_label(use_markup: bool, label: &str) -> gtk::Label {
-    gtk::Label::builder()
-        .wrap_mode(gtk::pango::WrapMode::Char)
-        .wrap(true)
-        .xalign(0.0)
-        .use_markup(use_markup)
-        .halign(gtk::Align::Start)
-        .valign(gtk::Align::Center)
-        .vexpand(true)
-        .label(label)
-        .build()
+fn to_ghit(hit: PluginHit) -> GHit {
+    let ghit = GHit::from(hit.hit);
+    ghit.set_plugin_pid(hit.pid);
+    ghit
 }
 
-pub fn build_image(icon: &str) -> gtk::Image {
-    let mut image = gtk::Image::builder().pixel_size(32);
-    let path = PathBuf::from(icon);
-
-    image = if path.is_absolute() {
-        image.file(path.to_string_lossy())
-    } else {
-        image.icon_name(icon)
+fn update_placeholder(runtime_data: &RuntimeData) {
+    let Some(widgets) = &runtime_data.widgets else {
+        return;
     };
-    image.build()
-}
 
-pub fn launch_plugins() {
-    if let Ok(current_exe_path) = current_exe() {
-        if let Some(current_dir) = current_exe_path.parent() {
-            if let Ok(entries) = read_dir(current_dir) {
-                let binaries_to_launch = entries
-                    .filter_map(Result::ok)
-                    .map(|entry| entry.path())
-                    .filter(|path| path.is_file())
-                    .filter(|path| {
-                        path.file_name()
-                            .and_then(|name| name.to_str())
-                            .map_or(false, |name| name.starts_with("unirun-plugin"))
-                    })
-                    .filter(|path| {
-                        path.metadata()
-                            .map_or(false, |metadata| metadata.permissions().mode() & 0o111 != 0)
-                    })
-                    .collect::<Vec<_>>();
-
-                for binary in binaries_to_launch {
-                    if let Err(e) = process::Command::new(&binary).spawn() {
-                        error!("Failed to launch {}: {}", binary.display(), e);
-                    }
-                }
-            } else {
-                error!("Failed to read directory: {}", current_dir.display());
-            }
-        } else {
-            error!("Failed to get parent directory of current executable");
-        }
-    } else {
-        error!("Failed to get current executable path");
-    }
+    let busy = runtime_data.host.is_busy();
+    widgets
+        .placeholder
+        .set_text(if busy { "Searching…" } else { "No results" });
 }
 
-pub fn clear_entry_pool(runtime_data: &mut RuntimeData) {
-    let entry_pool = &runtime_data.entry_pool;
-
-    if !entry_pool.is_empty() {
-        warn!(
-            "There is still {} running tasks. Aborting",
-            entry_pool.len()
-        );
-
-        entry_pool.iter().for_each(|jh| jh.abort());
-        runtime_data.entry_pool.clear();
-    }
+/// Requests next page from every plugin that may have more hits for current query
+pub fn load_more(runtime_data: Rc<RefCell<RuntimeData>>) {
+    runtime_data.borrow().host.load_more();
 }
 
 // pub fn filter_connections(runtime_data: &mut RuntimeData) {
@@ -160,131 +122,277 @@ pub fn clear_entry_pool(runtime_data: &mut RuntimeData) {
 //         .collect();
 // }
 
+/// How long hidden runner keeps clipboard content alive if nobody else takes it
+const CLIPBOARD_KEEP_ALIVE_SECS: u32 = 30;
+
+/// Quits application closed by user. If runner owns the clipboard then window is hidden
+/// and quit is delayed until clipboard is taken by someone else
+fn quit_keeping_clipboard(runtime_data: Rc<RefCell<RuntimeData>>) {
+    let application = runtime_data.borrow().application.clone();
+    let Some(widgets) = runtime_data.borrow().widgets.clone() else {
+        application.quit();
+        return;
+    };
+
+    let clipboard = widgets.window.clipboard();
+    if !clipboard.is_local() {
+        application.quit();
+        return;
+    }
+
+    info!("Keeping clipboard content alive before quit");
+    widgets.window.set_visible(false);
+
+    clipboard.connect_changed(clone!(
+        #[strong]
+        application,
+        move |clipboard| {
+            if !clipboard.is_local() {
+                application.quit()
+            }
+        }
+    ));
+    glib::timeout_add_seconds_local_once(CLIPBOARD_KEEP_ALIVE_SECS, move || application.quit());
+}
+
+/// Hides window if runner works as daemon, quits otherwise
+pub fn close_window(runtime_data: Rc<RefCell<RuntimeData>>) {
+    if runtime_data.borrow().host.config().daemon {
+        hide_window(runtime_data);
+    } else {
+        quit_keeping_clipboard(runtime_data);
+    }
+}
+
+/// Hidden window is reset to the root view with empty query
+pub fn hide_window(runtime_data: Rc<RefCell<RuntimeData>>) {
+    let Some(widgets) = runtime_data.borrow().widgets.clone() else {
+        return;
+    };
+
+    widgets.window.set_visible(false);
+    while pop_view(runtime_data.clone()) {}
+    widgets.entry.set_text("");
+}
+
+pub fn show_window(runtime_data: Rc<RefCell<RuntimeData>>) {
+    let Some(widgets) = runtime_data.borrow().widgets.clone() else {
+        return;
+    };
+
+    widgets.window.present();
+    widgets.entry.grab_focus();
+}
+
 pub fn on_entry_changed(text: &str, runtime_data: Rc<RefCell<RuntimeData>>) {
-    let mut runtime_data = runtime_data.borrow_mut();
+    let runtime_data = runtime_data.borrow();
 
-    clear_entry_pool(&mut runtime_data);
-    // filter_connections(&mut runtime_data);
+    if let Some(widgets) = &runtime_data.widgets {
+        widgets.message.set_visible(false);
+    }
+    if let Some(groups) = &runtime_data.groups {
+        groups.collapse_all();
+    }
 
-    let hit_store = runtime_data.hit_store.clone();
-    hit_store.remove_all();
+    runtime_data.host.query(text);
+}
 
-    let text = Rc::new(text.to_owned());
+pub fn handle_selection_activation(ghit: GHit, runtime_data: Rc<RefCell<RuntimeData>>) {
+    glib::spawn_future_local(async move {
+        let plugin_pid = ghit.get_plugin_pid();
+        let id = HitId::from(ghit.get_id().as_str());
+
+        let (host, application) = {
+            let runtime_data = runtime_data.borrow();
+            (runtime_data.host.clone(), runtime_data.application.clone())
+        };
+        if let Some(plugin) = host.plugin(plugin_pid) {
+            dbus::emit_activated(&application, &plugin.name(), &id.to_string());
+        }
 
-    for stream in runtime_data.connections.clone() {
+        if let Some(action) = host.activate(plugin_pid, id).await {
+            handle_action(action, plugin_pid, runtime_data);
+        }
+    });
+}
+
+/// Shows error in the window banner and as desktop notification if enabled
+fn show_plugin_error(runtime_data: &RuntimeData, plugin_pid: u64, error: &str) {
+    let name = runtime_data
+        .host
+        .plugin(plugin_pid)
+        .map(|plugin| plugin.name())
+        .unwrap_or_else(|| plugin_pid.to_string());
+
+    if let Some(widgets) = &runtime_data.widgets {
+        banner::show_error(&widgets.error_banner, &name, error);
+    }
+
+    if runtime_data.host.config().notifications {
+        let notification = gio::Notification::new(&format!("{} - {}", MAIN_WINDOW_TITLE, name));
+        notification.set_body(Some(error));
+        notification.set_icon(&gio::ThemedIcon::from_names(&[
+            MAIN_APP_ID,
+            "system-search",
+        ]));
         runtime_data
-            .entry_pool
-            .push(glib::spawn_future_local(clone!(
-                #[strong]
-                text,
-                #[strong]
-                hit_store,
-                async move {
-                    stream
-                        .write_future(Package::new(Payload::Command(Command::Abort)))
-                        .await
-                        .unwrap();
-
-                    let request =
-                        Package::new(Payload::Command(Command::GetData(text.to_string())));
-                    stream.write_future(request.clone()).await.unwrap();
-
-                    let request_id = request.get_id();
-                    // FIXME is this workaround?
-                    loop {
-                        if let Payload::Result((response_id, Ok(()))) =
-                            stream.read_future().await.unwrap().payload
-                        {
-                            if request_id == response_id {
-                                break;
-                            }
-                        }
-                    }
-
-                    loop {
-                        let response = stream.read_future().await.unwrap();
-                        let response_id = response.get_id();
-                        match response.payload {
-                            Payload::Hit(h) => {
-                                hit_store.append(&{
-                                    let ghit = GHit::from(h);
-                                    ghit.set_plugin_pid(
-                                        stream.credentials.unwrap().pid.unwrap() as u64
-                                    );
-                                    ghit
-                                });
-
-                                stream
-                                    .write_future(Package::new(Payload::Result((
-                                        response_id,
-                                        Ok(()),
-                                    ))))
-                                    .await
-                                    .unwrap();
-                            }
-                            Payload::Command(Command::Abort) => {
-                                break;
-                            }
-                            _ => unreachable!(),
-                        }
-                    }
-                }
-            )))
+            .application
+            .send_notification(None, &notification);
     }
 }
 
-pub fn handle_selection_activation(row_id: u32, runtime_data: Rc<RefCell<RuntimeData>>) {
-    glib::spawn_future_local(async move {
-        let ghit = runtime_data
-            .borrow()
-            .hit_store
-            .item(row_id)
-            .unwrap_or_else(|| panic!("Failed to get list_store item at {} position", row_id))
-            .downcast::<GHit>()
-            .expect("Failed to downcast Object to GHit");
+/// Embedded preview of `ghit` or the one requested from its plugin
+pub async fn request_preview(
+    ghit: &GHit,
+    runtime_data: Rc<RefCell<RuntimeData>>,
+) -> Option<Preview> {
+    if let Some(preview) = ghit.get_preview() {
+        return Some(preview);
+    }
 
-        let plugin_pid = ghit.get_plugin_pid();
+    let host = runtime_data.borrow().host.clone();
+    let preview = host
+        .preview(ghit.get_plugin_pid(), HitId::from(ghit.get_id().as_str()))
+        .await;
+    // remember so it's not requested again
+    ghit.set_preview(preview.clone());
+    preview
+}
+
+pub fn handle_action(action: Action, plugin_pid: u64, runtime_data: Rc<RefCell<RuntimeData>>) {
+    debug!("Handling activation action: {:?}", action);
+
+    let Some(widgets) = runtime_data.borrow().widgets.clone() else {
+        warn!("UI is not built yet. Ignoring {:?}", action);
+        return;
+    };
 
-        let connections = runtime_data.borrow().connections.clone();
-        let stream = connections
-            .iter()
-            .find(|stream| stream.credentials.unwrap().pid.unwrap() as u64 == plugin_pid)
-            .unwrap();
-
-        let hit: Hit = ghit.clone().into();
-        // TODO need to send Abort before Activate ?
-        let request = Package::new(Payload::Command(Command::Activate(hit.id.to_owned())));
-        stream.write_future(request.clone()).await.unwrap();
-        let request_id = request.get_id();
-
-        let response = stream.read_future().await.unwrap();
-
-        match response.payload {
-            Payload::Result((response_id, result)) => match result {
-                Ok(()) => {
-                    if response_id == request_id {
-                        Stream::new_future()
-                            .await
-                            .unwrap()
-                            .write_future(Package::new(Payload::Command(Command::Quit)))
-                            .await
-                            .unwrap();
-                    }
+    match action {
+        Action::Close => close_window(runtime_data),
+        Action::KeepOpen => {}
+        Action::SetQuery(query) => {
+            widgets.entry.set_text(&query);
+            widgets.entry.set_position(-1);
+        }
+        Action::Clipboard { content, close } => {
+            glib::spawn_future_local(async move {
+                if let Err(e) = set_clipboard_content(&widgets.window.clipboard(), content).await {
+                    error!("Failed to set clipboard content: {}", e);
                 }
-                Err(e) => {
-                    let notification = gio::Notification::new(MAIN_WINDOW_TITLE);
-                    notification.set_body(Some(&e));
-                    // TODO add desktop file?
-                    // https://docs.gtk.org/gio/class.Notification.html
-                    // notification.set_icon(&gio::ThemedIcon::new("dialog-error"));
-
-                    runtime_data
-                        .borrow()
-                        .application
-                        .send_notification(None, &notification);
+                if close {
+                    close_window(runtime_data);
                 }
-            },
-            _ => unreachable!(),
+            });
         }
-    });
+        Action::Message(message) => {
+            widgets.message.set_label(&message);
+            widgets.message.set_visible(true);
+        }
+        Action::Refresh => runtime_data.borrow().host.refresh(plugin_pid),
+        Action::Push { context, title } => {
+            push_view(plugin_pid, context, title, runtime_data.clone());
+
+            if widgets.entry.text().is_empty() {
+                on_entry_changed("", runtime_data);
+            } else {
+                widgets.entry.set_text("");
+            }
+        }
+    }
+}
+
+fn update_breadcrumbs(runtime_data: &RuntimeData) {
+    let Some(widgets) = &runtime_data.widgets else {
+        return;
+    };
+
+    let titles = runtime_data
+        .host
+        .view_titles()
+        .iter()
+        .map(|title| glib::markup_escape_text(title).to_string())
+        .collect::<Vec<_>>();
+    widgets.breadcrumbs.set_markup(&titles.join(" › "));
+    widgets.breadcrumbs.set_visible(!titles.is_empty());
+}
+
+pub fn push_view(
+    plugin_pid: u64,
+    context: String,
+    title: String,
+    runtime_data: Rc<RefCell<RuntimeData>>,
+) {
+    let runtime_data = runtime_data.borrow();
+    runtime_data.host.push_view(plugin_pid, context, title);
+    update_breadcrumbs(&runtime_data);
+}
+
+/// Returns to the parent view restoring its query and results. Returns `false` if there is no view to pop
+pub fn pop_view(runtime_data: Rc<RefCell<RuntimeData>>) -> bool {
+    let runtime_data = runtime_data.borrow();
+    if !runtime_data.host.pop_view() {
+        return false;
+    }
+    update_breadcrumbs(&runtime_data);
+
+    if let Some(widgets) = runtime_data.widgets.clone() {
+        let query = runtime_data.host.current_query();
+        if widgets.entry.text() != query {
+            // `search-changed` can be emitted right away so runtime_data must not be borrowed
+            drop(runtime_data);
+            widgets.entry.set_text(&query);
+            widgets.entry.set_position(-1);
+        }
+    }
+
+    true
+}
+
+pub async fn set_clipboard_content(
+    clipboard: &gdk::Clipboard,
+    content: ClipboardContent,
+) -> Result<(), Box<dyn Error>> {
+    const TEXT_MIME_TYPE: &str = "text/plain;charset=utf-8";
+
+    let provider = match content {
+        ClipboardContent::Text(text) => {
+            clipboard.set_text(&text);
+            return Ok(());
+        }
+        ClipboardContent::File { path, mime_type } => {
+            let file = gio::File::for_path(&path);
+            let (bytes, _) = file.load_bytes_future().await?;
+            let mime_type = mime_type.unwrap_or_else(|| {
+                let (content_type, _) = gio::content_type_guess(Some(&path), &bytes);
+                gio::content_type_get_mime_type(&content_type)
+                    .map(Into::into)
+                    .unwrap_or_else(|| "application/octet-stream".to_owned())
+            });
+
+            gdk::ContentProvider::new_union(&[
+                gdk::ContentProvider::for_bytes(&mime_type, &bytes),
+                gdk::ContentProvider::for_value(&file.to_value()),
+            ])
+        }
+        ClipboardContent::Data { mime_type, data } => {
+            let bytes = glib::Bytes::from_owned(data.into_bytes());
+            if mime_type.starts_with("text/") && mime_type != TEXT_MIME_TYPE {
+                gdk::ContentProvider::new_union(&[
+                    gdk::ContentProvider::for_bytes(&mime_type, &bytes),
+                    gdk::ContentProvider::for_bytes(TEXT_MIME_TYPE, &bytes),
+                ])
+            } else {
+                gdk::ContentProvider::for_bytes(&mime_type, &bytes)
+            }
+        }
+    };
+
+    clipboard.set_content(Some(&provider))?;
+    Ok(())
+}
+
+pub fn copy_hit(ghit: &GHit, clipboard: &gdk::Clipboard) {
+    let text = ghit.copy_text();
+    debug!("Copying {:?}", text);
+    clipboard.set_text(&text);
 }
@@ -0,0 +1,66 @@
+#!/bin/sh
+# Calls D-Bus interface of unirun on a private bus with mock plugin connected.
+# Needs display-less GTK backend (broadway) and binaries built by `cargo build --workspace`
+#
+#   dbus-run-session -- unirun/tests/dbus.sh [target/debug]
+
+set -eu
+
+BIN=${1:-target/debug}
+RUNTIME=$(mktemp -d)
+export XDG_RUNTIME_DIR="$RUNTIME" XDG_CONFIG_HOME="$RUNTIME/config" GDK_BACKEND=broadway
+trap 'kill $(jobs -p) 2>/dev/null; rm -rf "$RUNTIME"' EXIT
+
+call() {
+    method=$1
+    shift
+    gdbus call --session --dest com.bzglve.unirun --object-path /com/bzglve/unirun \
+        --method "com.bzglve.unirun.$method" "$@"
+}
+
+fail() {
+    echo "FAIL: $*" >&2
+    exit 1
+}
+
+gtk4-broadwayd :5 >/dev/null 2>&1 &
+export BROADWAY_DISPLAY=:5
+"$BIN/unirun" &
+for _ in $(seq 50); do
+    call ListPlugins >/dev/null 2>&1 && break
+    sleep 0.1
+done
+
+UNIRUN_MOCK_SCRIPT='{
+    "name": "mock",
+    "hits": [{
+        "id": "6f9619ff-8b86-d011-b42d-00c04fc964ff",
+        "title": "mock {query}",
+        "description": null,
+        "icon": null,
+        "use_pango": false
+    }]
+}' "$BIN/unirun-mock-plugin" &
+for _ in $(seq 50); do
+    call ListPlugins | grep -q "'mock'" && break
+    sleep 0.1
+done
+call ListPlugins | grep -q "'mock'" || fail "mock plugin didn't register"
+
+gdbus monitor --session --dest com.bzglve.unirun >"$RUNTIME/signals" &
+sleep 0.2
+
+call Hide >/dev/null
+hits=$(call Query "hidden")
+echo "$hits" | grep -q "'mock hidden'" || fail "Query with hidden window: $hits"
+
+call Show "" >/dev/null
+hits=$(call Query "visible")
+echo "$hits" | grep -q "'mock visible'" || fail "Query with visible window: $hits"
+
+call Toggle >/dev/null
+sleep 0.2
+grep -q "VisibilityChanged (true,)" "$RUNTIME/signals" || fail "no VisibilityChanged(true)"
+grep -q "VisibilityChanged (false,)" "$RUNTIME/signals" || fail "no VisibilityChanged(false)"
+
+echo "PASS"